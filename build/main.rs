@@ -0,0 +1,17 @@
+use std::env;
+use std::path::PathBuf;
+
+mod op_gen;
+
+fn main() {
+    let mxnet_path = env::var("MXNET_PATH").expect("MXNET_PATH not defined");
+    println!("cargo:rustc-env=LD_LIBRARY_PATH={}", mxnet_path);
+    println!("cargo:rerun-if-env-changed=MXNET_PATH");
+    println!("cargo:rerun-if-changed=build");
+
+    // The op modules are generated from the operators registered in the
+    // libmxnet we link against, so they always match its version.
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+    op_gen::generate(op_gen::Target::NDArray, &out_dir.join("ndarray_op.rs"));
+    op_gen::generate(op_gen::Target::Symbol, &out_dir.join("symbol_op.rs"));
+}
@@ -0,0 +1,1029 @@
+//! Generate a typed builder per registered MXNet operator.
+//!
+//! Operators are grouped like the Python frontend: `_contrib_`, `_linalg_`,
+//! `_sparse_`, `_image_` and `_random_` prefixed ops go into their own
+//! module with the prefix stripped, other underscored ops into `internal`
+//! and everything else into `op`.
+
+use codegen::{Enum, Function, Impl, Scope, Struct};
+use mxnet_sys::*;
+use std::collections::{BTreeMap, BTreeSet};
+use std::ffi::{CStr, CString};
+use std::fs;
+use std::os::raw::c_char;
+use std::path::Path;
+use std::ptr;
+use std::slice;
+
+// Same as `mxnet_rs::base::OP_NAME_PREFIX_LIST`, the build script can't
+// depend on the crate it builds.
+const OP_NAME_PREFIX_LIST: &[&str] = &["_contrib_", "_linalg_", "_sparse_", "_image_", "_random_"];
+
+// Names `DType` displays as.
+const DTYPE_NAMES: &[&str] = &[
+    "float32", "float64", "float16", "uint8", "int32", "int8", "int64",
+];
+
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "do", "dyn",
+    "else", "enum", "extern", "false", "final", "fn", "for", "if", "impl", "in", "let", "loop",
+    "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref", "return", "static",
+    "struct", "trait", "true", "try", "type", "typeof", "unsafe", "unsized", "use", "virtual",
+    "where", "while", "yield",
+];
+
+// Output names of ops returning a fixed number of arrays.
+const MULTI_OUTPUT_OPS: &[(&str, &[&str])] = &[
+    (
+        "_contrib_MultiBoxTarget",
+        &["loc_target", "loc_mask", "cls_target"],
+    ),
+    ("_contrib_quantize", &["output", "min_output", "max_output"]),
+    (
+        "_contrib_quantize_v2",
+        &["output", "min_output", "max_output"],
+    ),
+    (
+        "_contrib_requantize",
+        &["output", "min_output", "max_output"],
+    ),
+    ("_linalg_gelqf", &["q", "l"]),
+    ("_linalg_slogdet", &["sign", "logabsdet"]),
+    ("_linalg_syevd", &["u", "l"]),
+    ("moments", &["mean", "var"]),
+];
+
+// Inputs that composing a symbol creates as variables when left out, e.g.
+// `fc1_weight`. Operands like `lhs` and `rhs` stay required.
+const LEARNABLE_INPUTS: &[&str] = &[
+    "weight",
+    "bias",
+    "gamma",
+    "beta",
+    "moving_mean",
+    "moving_var",
+    "parameters",
+];
+
+// Ops returning more outputs when a param is set: the param, its value and
+// the output names. They get `invoke_all` / `create_all` setting it.
+const OPTIONAL_OUTPUT_OPS: &[(&str, &str, &str, &[&str])] = &[
+    (
+        "BatchNorm",
+        "output_mean_var",
+        "true",
+        &["output", "mean", "var"],
+    ),
+    ("topk", "ret_typ", "both", &["value", "indices"]),
+];
+
+// Ops returning as many outputs as their params say, besides those with a
+// `num_outputs` param like `split`.
+const VARIABLE_OUTPUT_OPS: &[&str] = &["_split_v2"];
+
+macro_rules! check_call {
+    ($func:ident($($arg:expr),* $(,)*)) => {
+        if unsafe { $func($($arg),*) } != 0 {
+            let message = unsafe { CStr::from_ptr(MXGetLastError()) };
+            panic!("{} failed: {}", stringify!($func), message.to_string_lossy());
+        }
+    };
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Target {
+    NDArray,
+    Symbol,
+}
+
+impl Target {
+    fn type_name(self) -> &'static str {
+        match self {
+            Target::NDArray => "NDArray",
+            Target::Symbol => "Symbol",
+        }
+    }
+
+    fn module_path(self) -> &'static str {
+        match self {
+            Target::NDArray => "crate::ndarray",
+            Target::Symbol => "crate::symbol",
+        }
+    }
+}
+
+struct OpArg {
+    name: String,
+    type_info: String,
+    description: String,
+}
+
+struct OpInfo {
+    name: String,
+    description: String,
+    args: Vec<OpArg>,
+    key_var_num_args: String,
+}
+
+impl OpInfo {
+    fn new(name: &str) -> OpInfo {
+        let c_name = CString::new(name).unwrap();
+        let mut handle = ptr::null_mut();
+        check_call!(NNGetOpHandle(c_name.as_ptr(), &mut handle));
+
+        let mut real_name = ptr::null();
+        let mut description = ptr::null();
+        let mut num_args = 0;
+        let mut arg_names = ptr::null_mut();
+        let mut arg_type_infos = ptr::null_mut();
+        let mut arg_descriptions = ptr::null_mut();
+        let mut key_var_num_args = ptr::null();
+        let mut return_type = ptr::null();
+
+        check_call!(MXSymbolGetAtomicSymbolInfo(
+            handle,
+            &mut real_name,
+            &mut description,
+            &mut num_args,
+            &mut arg_names,
+            &mut arg_type_infos,
+            &mut arg_descriptions,
+            &mut key_var_num_args,
+            &mut return_type
+        ));
+
+        let arg_names = to_string_vec(num_args, arg_names);
+        let arg_type_infos = to_string_vec(num_args, arg_type_infos);
+        let arg_descriptions = to_string_vec(num_args, arg_descriptions);
+        let args = arg_names
+            .into_iter()
+            .zip(arg_type_infos)
+            .zip(arg_descriptions)
+            .map(|((name, type_info), description)| OpArg {
+                name,
+                type_info,
+                description,
+            })
+            .collect();
+
+        OpInfo {
+            name: name.to_owned(),
+            description: optional_string(description),
+            args,
+            key_var_num_args: optional_string(key_var_num_args),
+        }
+    }
+
+    fn has_arg(&self, name: &str) -> bool {
+        self.args.iter().any(|arg| arg.name == name)
+    }
+
+    fn has_inputs(&self) -> bool {
+        self.args
+            .iter()
+            .any(|arg| is_tensor(parse_type_info(&arg.type_info).0))
+    }
+
+    fn is_variadic(&self) -> bool {
+        self.args.iter().any(|arg| {
+            let (ty, _) = parse_type_info(&arg.type_info);
+            is_tensor(ty) && ty.ends_with("[]")
+        })
+    }
+}
+
+/// How an operator argument shows up in the generated signature.
+enum ArgKind {
+    /// A single tensor input.
+    Input { optional: bool },
+    /// `NDArray-or-Symbol[]`, its length goes into `key_var_num_args`.
+    Inputs,
+    /// Anything passed through `Operator::set_param`.
+    Param { ty: String, optional: bool },
+}
+
+fn arg_kind(target: Target, info: &OpInfo, arg: &OpArg) -> ArgKind {
+    let (ty, optional) = parse_type_info(&arg.type_info);
+    if is_tensor(ty) {
+        if ty.ends_with("[]") {
+            ArgKind::Inputs
+        } else {
+            ArgKind::Input {
+                optional: is_optional_input(target, info, &arg.name),
+            }
+        }
+    } else {
+        let (ty, nullable) = rust_type(ty);
+        ArgKind::Param {
+            ty,
+            optional: optional || nullable,
+        }
+    }
+}
+
+fn is_tensor(ty: &str) -> bool {
+    ty.starts_with("NDArray") || ty.starts_with("Symbol")
+}
+
+/// MXNet declares every tensor input as required, but some are dropped by
+/// a parameter, e.g. `bias` when `no_bias` is set. Composing a symbol
+/// also creates a variable for each learnable input left out, like
+/// `fc1_weight`, so those are optional there.
+fn is_optional_input(target: Target, info: &OpInfo, input: &str) -> bool {
+    (target == Target::Symbol && is_learnable(input))
+        || info.has_arg(&format!("no_{}", input))
+        || (input == "sequence_length" && info.has_arg("use_sequence_length"))
+        || (input == "state_cell" && info.name == "RNN")
+}
+
+fn is_learnable(input: &str) -> bool {
+    LEARNABLE_INPUTS.contains(&input) || input.ends_with("_weight") || input.ends_with("_bias")
+}
+
+/// Split a type string like `{'avg', 'max'},optional, default='max'` into
+/// the type and whether the argument is optional.
+fn parse_type_info(type_info: &str) -> (&str, bool) {
+    let end = if type_info.starts_with('{') {
+        type_info.find('}').map_or(type_info.len(), |i| i + 1)
+    } else {
+        type_info.find(',').unwrap_or(type_info.len())
+    };
+    let optional = type_info[end..].contains("optional");
+    (type_info[..end].trim(), optional)
+}
+
+/// The declared default of an optional argument, as shown in the docs.
+fn default_value(type_info: &str, ty: &str) -> Option<String> {
+    let value = type_info.split("default=").nth(1)?.trim();
+    let value = match (ty, value.trim_matches('\'')) {
+        ("bool", "0") | ("bool", "False") => "false",
+        ("bool", "1") | ("bool", "True") => "true",
+        (_, "") => "\"\"",
+        (_, value) => value,
+    };
+    Some(value.to_owned())
+}
+
+/// Values of an enum type string like `{None, 'float16', 'float32'}`.
+fn enum_values(ty: &str) -> Vec<String> {
+    ty.trim_matches(|c| c == '{' || c == '}')
+        .split(',')
+        .map(|value| value.trim().trim_matches('\'').to_owned())
+        .filter(|value| !value.is_empty())
+        .collect()
+}
+
+/// Map an MXNet type string to a Rust type, and whether `None` is allowed.
+fn rust_type(ty: &str) -> (String, bool) {
+    let (ty, or_none) = match ty.strip_suffix(" or None") {
+        Some(ty) => (ty, true),
+        None => (ty, false),
+    };
+
+    if ty.starts_with('{') {
+        let values = enum_values(ty);
+        let nullable = values.iter().any(|value| value == "None");
+        let is_dtype = values
+            .iter()
+            .filter(|value| *value != "None")
+            .all(|value| DTYPE_NAMES.contains(&value.as_str()));
+        // Other sets are kept as is and become enums in `OpModule::enum_type`.
+        let ty = if is_dtype { "DType" } else { ty };
+        return (ty.to_owned(), or_none || nullable);
+    }
+
+    let ty = match ty {
+        "boolean" => "bool",
+        "int" => "i32",
+        "int (non-negative)" => "u32",
+        "long" | "int64" => "i64",
+        "long (non-negative)" => "u64",
+        "float" | "real_t" => "f32",
+        "double" => "f64",
+        "Shape(tuple)" => "Shape",
+        "tuple of <int>" => "Tuple<i32>",
+        "tuple of <long>" => "Tuple<i64>",
+        "tuple of <float>" => "Tuple<f32>",
+        "tuple of <double>" => "Tuple<f64>",
+        // "string" and anything unknown is passed through as is.
+        _ => "&str",
+    };
+    (ty.to_owned(), or_none)
+}
+
+/// Find the module and snake case name of an operator.
+fn locate(op_name: &str) -> (&'static str, String) {
+    let prefix = OP_NAME_PREFIX_LIST
+        .iter()
+        .find(|prefix| op_name.starts_with(*prefix));
+
+    match prefix {
+        // Plain `_random_*` ops are wrapped by hand, like in Python.
+        Some(prefix) if *prefix != "_random_" || op_name.ends_with("_like") => (
+            prefix.trim_matches('_'),
+            to_snake_case(&op_name[prefix.len()..]),
+        ),
+        _ if op_name.starts_with('_') => ("internal", to_snake_case(op_name)),
+        _ => ("op", to_snake_case(op_name)),
+    }
+}
+
+fn to_camel_case(snake: &str) -> String {
+    snake
+        .split('_')
+        .filter(|word| !word.is_empty())
+        .map(|word| word[..1].to_uppercase() + &word[1..])
+        .collect()
+}
+
+fn to_snake_case(name: &str) -> String {
+    // `LeakyReLU` should become `leaky_relu`, not `leaky_re_lu`.
+    let name = name.replace("ReLU", "Relu");
+    let chars: Vec<char> = name.chars().collect();
+    let mut snake = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        if i > 0 && c.is_ascii_uppercase() {
+            let prev = chars[i - 1];
+            let next_is_lower = chars.get(i + 1).is_some_and(|c| c.is_ascii_lowercase());
+            if prev.is_ascii_lowercase()
+                || prev.is_ascii_digit()
+                || (prev.is_ascii_uppercase() && next_is_lower)
+            {
+                snake.push('_');
+            }
+        }
+        snake.push(c.to_ascii_lowercase());
+    }
+    snake
+}
+
+/// A Rust identifier for an operator or argument name.
+fn ident(name: &str) -> String {
+    let name = to_snake_case(name);
+    match name.as_str() {
+        // Builder fields and methods, and keywords that can't be raw.
+        "op" | "name" | "new" | "operator" | "invoke" | "invoke_with" | "try_invoke"
+        | "try_invoke_with" | "create" | "try_create" | "self" | "super" | "crate" => {
+            format!("{}_", name)
+        }
+        name if KEYWORDS.contains(&name) => format!("r#{}", name),
+        _ => name,
+    }
+}
+
+/// Names the builders can't take because of the module imports.
+const RESERVED_TYPE_NAMES: &[&str] = &[
+    "DType", "NDArray", "Operator", "Result", "Shape", "Symbol", "Tuple",
+];
+
+fn struct_name(snake: &str) -> String {
+    let name = to_camel_case(snake);
+    if RESERVED_TYPE_NAMES.contains(&name.as_str()) {
+        format!("{}Op", name)
+    } else {
+        name
+    }
+}
+
+/// MXNet descriptions are reStructuredText. Unindent them so rustdoc doesn't
+/// take indented blocks for doctests.
+fn doc_text(description: &str) -> String {
+    let mut lines: Vec<&str> = Vec::new();
+    for line in description.lines().map(str::trim) {
+        if !line.is_empty() || lines.last().is_some_and(|last| !last.is_empty()) {
+            lines.push(line);
+        }
+    }
+    while lines.last() == Some(&"") {
+        lines.pop();
+    }
+    lines.join("\n")
+}
+
+fn one_line(description: &str) -> String {
+    description.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn impl_block(struct_name: &str, has_lifetime: bool) -> Impl {
+    let mut imp = Impl::new(struct_name);
+    if has_lifetime {
+        imp.generic("'a").target_generic("'a");
+    }
+    imp
+}
+
+/// The builder struct of an operator and its impl blocks.
+fn make_builder(
+    target: Target,
+    info: &OpInfo,
+    struct_name: &str,
+    module: &mut OpModule,
+) -> Builder {
+    let tensor = format!("&'a {}", target.type_name());
+    let has_lifetime = info.has_inputs();
+    let variadic = info.is_variadic();
+
+    let mut doc = doc_text(&info.description);
+    if !doc.is_empty() {
+        doc.push_str("\n\n");
+    }
+    doc.push_str(&format!("Builder for the `{}` operator.", info.name));
+
+    let mut builder = Struct::new(struct_name);
+    builder
+        .vis("pub")
+        .doc(&doc)
+        .field("op", "Operator")
+        .field("error", "Option<Error>");
+    if has_lifetime {
+        builder.generic("'a");
+    }
+    let mut structs = vec![];
+
+    let mut output_struct = |outputs: &[&str]| {
+        let name = format!("{}Output", struct_name);
+        let fields: Vec<String> = outputs.iter().map(|output| ident(output)).collect();
+        let mut output = Struct::new(&name);
+        output
+            .vis("pub")
+            .doc(&format!("Outputs of the `{}` operator.", info.name))
+            .derive("Clone");
+        for field in &fields {
+            output.field(&format!("pub {}", field), target.type_name());
+        }
+        structs.push(output);
+        (name, fields)
+    };
+    let outputs =
+        if let Some((_, outputs)) = MULTI_OUTPUT_OPS.iter().find(|(name, _)| *name == info.name) {
+            let (name, fields) = output_struct(outputs);
+            Outputs::Named(name, fields)
+        } else if info.has_arg("num_outputs") || VARIABLE_OUTPUT_OPS.contains(&info.name.as_str()) {
+            Outputs::Many
+        } else {
+            Outputs::One
+        };
+    let all_outputs = OPTIONAL_OUTPUT_OPS
+        .iter()
+        .find(|(name, ..)| *name == info.name)
+        .map(|(_, param, value, outputs)| {
+            let (name, fields) = output_struct(outputs);
+            (*param, *value, name, fields)
+        });
+
+    let mut new = Function::new("new");
+    let mut new_doc = String::from("Create the operator from its required inputs.\n");
+    let mut fields = vec![
+        format!("op: Operator::new({:?})", info.name),
+        "error: None".to_owned(),
+    ];
+    new.vis("pub").ret("Self");
+
+    let mut setters = Vec::new();
+    // Inputs are pushed when the operator is built, so they keep their
+    // declared order whatever order the setters are called in.
+    let mut push_inputs = Vec::new();
+
+    for arg in &info.args {
+        let name = ident(&arg.name);
+        let description = one_line(&arg.description);
+        // Symbols are composed by keyword, unless a variable number of
+        // inputs makes them positional.
+        let push = |value: &str| {
+            if target == Target::NDArray || variadic {
+                format!("op.push_input({});", value)
+            } else {
+                format!("op.set_input({:?}, {});", arg.name, value)
+            }
+        };
+
+        match arg_kind(target, info, arg) {
+            ArgKind::Input { optional: false } => {
+                builder.field(&name, tensor.as_str());
+                new.arg(&name, tensor.as_str());
+                new_doc.push_str(&format!("\n- `{}`: {}", name, description));
+                fields.push(name.clone());
+                push_inputs.push(push(&format!("self.{}", name)));
+            }
+            ArgKind::Input { optional: true } => {
+                builder.field(&name, format!("Option<{}>", tensor));
+                fields.push(format!("{}: None", name));
+                push_inputs.push(format!(
+                    "if let Some({0}) = self.{0} {{ {1} }}",
+                    name,
+                    push(&name)
+                ));
+
+                let mut setter = Function::new(&name);
+                setter
+                    .vis("pub")
+                    .doc(&description)
+                    .arg_mut_self()
+                    .arg(&name, tensor.as_str())
+                    .ret("&mut Self")
+                    .line(format!("self.{0} = Some({0});", name))
+                    .line("self");
+                setters.push(setter);
+            }
+            ArgKind::Inputs => {
+                let ty = format!("&'a [{}]", tensor);
+                builder.field(&name, ty.as_str());
+                new.arg(&name, ty);
+                new_doc.push_str(&format!("\n- `{}`: {}", name, description));
+                fields.push(name.clone());
+                // Also sets `key_var_num_args`.
+                push_inputs.push(format!("op.try_push_inputs(self.{})?;", name));
+            }
+            // Set by `push_inputs`.
+            ArgKind::Param { .. } if variadic && arg.name == info.key_var_num_args => {}
+            ArgKind::Param { ty, optional } => {
+                let is_enum = ty.starts_with('{');
+                let ty = if is_enum {
+                    module.enum_type(struct_name, &arg.name, &ty)
+                } else {
+                    add_imports(&ty, &mut module.imports);
+                    ty
+                };
+                // Tuples take anything that converts, e.g. `(3, 3)`.
+                let (arg_ty, value) = if ty.starts_with("Shape") || ty.starts_with("Tuple") {
+                    (format!("impl Into<{}>", ty), format!("&{}.into()", name))
+                } else {
+                    (ty.clone(), format!("&{}", name))
+                };
+
+                let mut doc = description;
+                let note = if optional {
+                    default_value(&arg.type_info, &ty)
+                        .map(|value| match value.as_str() {
+                            "None" => value,
+                            _ if is_enum => format!("{}::{}", ty, variant_name(&value)),
+                            _ => value,
+                        })
+                        .map(|value| format!("Defaults to `{}`.", value))
+                } else {
+                    Some("Required.".to_owned())
+                };
+                if let Some(note) = note {
+                    if !doc.is_empty() {
+                        doc.push_str("\n\n");
+                    }
+                    doc.push_str(&note);
+                }
+
+                let mut setter = Function::new(&name);
+                setter
+                    .vis("pub")
+                    .doc(&doc)
+                    .arg_mut_self()
+                    .arg(&name, arg_ty)
+                    .ret("&mut Self")
+                    // The first invalid value is kept and returned when the
+                    // operator is built.
+                    .line("if self.error.is_none() {")
+                    .line(format!(
+                        "    self.error = self.op.try_set_param({:?}, {}).err();",
+                        arg.name, value
+                    ))
+                    .line("}")
+                    .line("self");
+                setters.push(setter);
+            }
+        }
+    }
+
+    if target == Target::Symbol {
+        builder.field("name", "Option<String>");
+        fields.push("name: None".to_owned());
+
+        let mut setter = Function::new("name");
+        setter
+            .vis("pub")
+            .doc("Name of the created symbol.")
+            .arg_mut_self()
+            .arg("name", "&str")
+            .ret("&mut Self")
+            .line("self.name = Some(name.to_owned());")
+            .line("self");
+        setters.push(setter);
+    }
+
+    let mut imp = impl_block(struct_name, has_lifetime);
+    new.doc(new_doc.trim_end())
+        .line(format!("{} {{ {} }}", struct_name, fields.join(", ")));
+    imp.push_fn(new);
+    for setter in setters {
+        imp.push_fn(setter);
+    }
+
+    let mut operator = Function::new("operator");
+    operator
+        .vis("pub")
+        .doc("Collect the inputs and params into an `Operator`.")
+        .arg_ref_self()
+        .ret("Operator")
+        .line("self.try_operator().unwrap()");
+    imp.push_fn(operator);
+
+    let mut try_operator = Function::new("try_operator");
+    try_operator
+        .vis("pub")
+        .doc("Like `operator`, but returns the first invalid param a setter was given.")
+        .arg_ref_self()
+        .ret("Result<Operator>")
+        .line("if let Some(err) = &self.error {")
+        .line("    return Err(err.clone());")
+        .line("}");
+    if push_inputs.is_empty() {
+        try_operator.line("Ok(self.op.clone())");
+    } else {
+        try_operator.line("let mut op = self.op.clone();");
+        for line in push_inputs {
+            try_operator.line(line);
+        }
+        try_operator.line("Ok(op)");
+    }
+    imp.push_fn(try_operator);
+
+    for terminal in terminals(target, &outputs) {
+        imp.push_fn(terminal);
+    }
+    if let Some((param, value, name, fields)) = &all_outputs {
+        for terminal in all_terminals(target, param, value, name, fields) {
+            imp.push_fn(terminal);
+        }
+    }
+
+    let mut impls = vec![imp];
+
+    // Ops without required inputs, e.g. `_zeros`.
+    if !info.args.iter().any(|arg| {
+        matches!(
+            arg_kind(target, info, arg),
+            ArgKind::Input { optional: false } | ArgKind::Inputs
+        )
+    }) {
+        let mut default = impl_block(struct_name, has_lifetime);
+        default
+            .impl_trait("Default")
+            .new_fn("default")
+            .ret("Self")
+            .line("Self::new()");
+        impls.push(default);
+    }
+
+    structs.insert(0, builder);
+    (structs, impls)
+}
+
+/// What the terminals of a builder return.
+enum Outputs {
+    /// A single array or symbol.
+    One,
+    /// Several, as a struct with the given name and fields.
+    Named(String, Vec<String>),
+    /// As many as a param says, e.g. `num_outputs` of `split`.
+    Many,
+}
+
+/// Add a `pub fn name(&self, args) -> ret { line }` to `functions`.
+fn add_fn(
+    functions: &mut Vec<Function>,
+    name: &str,
+    args: &[(&str, &str)],
+    ret: Option<&str>,
+    line: &str,
+) {
+    let mut f = Function::new(name);
+    f.vis("pub").arg_ref_self();
+    for (name, ty) in args {
+        f.arg(name, *ty);
+    }
+    if let Some(ret) = ret {
+        f.ret(ret);
+    }
+    f.line(line);
+    functions.push(f);
+}
+
+/// The methods running a builder.
+fn terminals(target: Target, outputs: &Outputs) -> Vec<Function> {
+    let mut functions = Vec::new();
+    let mut add = |name: &str, args: &[(&str, &str)], ret: Option<&str>, line: &str| {
+        add_fn(&mut functions, name, args, ret, line)
+    };
+
+    match (target, outputs) {
+        (_, Outputs::Named(name, fields)) => {
+            return named_terminals(target, "", "self.try_operator()?", name, fields);
+        }
+        (Target::NDArray, Outputs::Many) => {
+            add(
+                "invoke",
+                &[],
+                Some("Vec<NDArray>"),
+                "self.try_invoke().unwrap()",
+            );
+            add(
+                "try_invoke",
+                &[],
+                Some("Result<Vec<NDArray>>"),
+                "self.try_operator()?.try_invoke_many()",
+            );
+        }
+        (Target::Symbol, Outputs::Many) => {
+            add(
+                "create",
+                &[],
+                Some("Vec<Symbol>"),
+                "self.try_create().unwrap()",
+            );
+            add(
+                "try_create",
+                &[],
+                Some("Result<Vec<Symbol>>"),
+                "self.try_operator()?.try_create_symbol_many(self.name.as_deref())",
+            );
+        }
+        (Target::NDArray, Outputs::One) => {
+            add("invoke", &[], Some("NDArray"), "self.try_invoke().unwrap()");
+            add(
+                "try_invoke",
+                &[],
+                Some("Result<NDArray>"),
+                "self.try_operator()?.try_invoke()",
+            );
+            add(
+                "invoke_with",
+                &[("output", "&mut NDArray")],
+                None,
+                "self.try_invoke_with(output).unwrap()",
+            );
+            add(
+                "try_invoke_with",
+                &[("output", "&mut NDArray")],
+                Some("Result<()>"),
+                "self.try_operator()?.try_invoke_with(output)",
+            );
+        }
+        (Target::Symbol, Outputs::One) => {
+            add("create", &[], Some("Symbol"), "self.try_create().unwrap()");
+            add(
+                "try_create",
+                &[],
+                Some("Result<Symbol>"),
+                "self.try_operator()?.try_create_symbol(self.name.as_deref())",
+            );
+        }
+    }
+
+    functions
+}
+
+/// `invoke_all` / `create_all` of the ops in `OPTIONAL_OUTPUT_OPS`, setting
+/// `param` to `value` to get every output.
+fn all_terminals(
+    target: Target,
+    param: &str,
+    value: &str,
+    name: &str,
+    fields: &[String],
+) -> Vec<Function> {
+    let op = format!(
+        "self.try_operator()?.try_set_param({:?}, &{:?})?",
+        param, value
+    );
+    let mut functions = named_terminals(target, "_all", &op, name, fields);
+    for f in &mut functions {
+        f.doc(&format!(
+            "Run with `{}` set to `{}`, returning every output.",
+            param, value
+        ));
+    }
+    functions
+}
+
+/// Terminals returning the outputs as the struct `name` with `fields`,
+/// running the operator built by the expression `op`.
+fn named_terminals(
+    target: Target,
+    suffix: &str,
+    op: &str,
+    name: &str,
+    fields: &[String],
+) -> Vec<Function> {
+    let mut functions = Vec::new();
+    let fields = fields.join(", ");
+    let wrap = format!(".map(|[{0}]| {1} {{ {0} }})", fields, name);
+    let result = format!("Result<{}>", name);
+    let (run, try_run) = match target {
+        Target::NDArray => (
+            format!("invoke{}", suffix),
+            format!("{}.try_invoke_n(){}", op, wrap),
+        ),
+        Target::Symbol => (
+            format!("create{}", suffix),
+            format!("{}.try_create_symbol_n(self.name.as_deref()){}", op, wrap),
+        ),
+    };
+    add_fn(
+        &mut functions,
+        &run,
+        &[],
+        Some(name),
+        &format!("self.try_{}().unwrap()", run),
+    );
+    add_fn(
+        &mut functions,
+        &format!("try_{}", run),
+        &[],
+        Some(&result),
+        &try_run,
+    );
+    functions
+}
+
+fn add_imports(ty: &str, imports: &mut BTreeSet<&'static str>) {
+    for name in &["DType", "Shape", "Tuple"] {
+        if ty.contains(name) {
+            imports.insert(*name);
+        }
+    }
+}
+
+/// A builder struct, the struct of its outputs if it has several, and their
+/// impl blocks.
+type Builder = (Vec<Struct>, Vec<Impl>);
+
+/// Everything generated into one module.
+#[derive(Default)]
+struct OpModule {
+    /// (op name, builder name)
+    ops: Vec<(String, String)>,
+    builders: Vec<Builder>,
+    /// Enum name => values. Params with the same values share an enum.
+    enums: BTreeMap<String, Vec<String>>,
+    imports: BTreeSet<&'static str>,
+}
+
+impl OpModule {
+    /// Name of the enum for the `{'a', 'b'}` type of `param`, taking the
+    /// param name unless another op already uses it for other values.
+    fn enum_type(&mut self, struct_name: &str, param: &str, ty: &str) -> String {
+        let values: Vec<String> = enum_values(ty)
+            .into_iter()
+            .filter(|value| value != "None")
+            .collect();
+        let name = to_camel_case(&to_snake_case(param));
+
+        for candidate in [name.clone(), format!("{}{}", struct_name, name)] {
+            let taken = RESERVED_TYPE_NAMES.contains(&candidate.as_str())
+                || self.ops.iter().any(|(_, builder)| *builder == candidate);
+            match self.enums.get(&candidate) {
+                _ if taken => {}
+                Some(existing) if *existing == values => return candidate,
+                Some(_) => {}
+                None => {
+                    self.enums.insert(candidate.clone(), values);
+                    return candidate;
+                }
+            }
+        }
+        panic!("no enum name for {} of {}", param, struct_name);
+    }
+}
+
+fn variant_name(value: &str) -> String {
+    let value: String = value
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    let name = to_camel_case(&to_snake_case(&value));
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("V{}", name)
+    } else {
+        name
+    }
+}
+
+fn make_enum(name: &str, values: &[String]) -> (Enum, Impl) {
+    let mut param_enum = Enum::new(name);
+    param_enum
+        .vis("pub")
+        .derive("Debug")
+        .derive("Copy")
+        .derive("Clone")
+        .derive("PartialEq");
+    for value in values {
+        param_enum.new_variant(&variant_name(value));
+    }
+
+    let mut display = Impl::new(name);
+    let fmt = display
+        .impl_trait("fmt::Display")
+        .new_fn("fmt")
+        .arg_ref_self()
+        .arg("f", "&mut fmt::Formatter")
+        .ret("fmt::Result")
+        .line("let value = match self {");
+    for value in values {
+        fmt.line(format!("{}::{} => {:?},", name, variant_name(value), value));
+    }
+    fmt.line("};").line("f.write_str(value)");
+
+    (param_enum, display)
+}
+
+/// Write the op modules for `target` to `path`.
+pub fn generate(target: Target, path: &Path) {
+    let mut modules: BTreeMap<&str, OpModule> = BTreeMap::new();
+
+    for op_name in list_all_op_names() {
+        // Only used by autograd.
+        if op_name.starts_with("_backward_") {
+            continue;
+        }
+
+        let (module_name, snake_name) = locate(&op_name);
+        let struct_name = struct_name(&snake_name);
+        let module = modules.entry(module_name).or_default();
+        // Aliases like `Flatten` and `flatten` end up with the same name.
+        if module.ops.iter().all(|(_, name)| *name != struct_name) {
+            module.ops.push((op_name, struct_name));
+        }
+    }
+
+    // Builder names are known up front so enums don't take them.
+    for module in modules.values_mut() {
+        for (op_name, struct_name) in module.ops.clone() {
+            let info = OpInfo::new(&op_name);
+            let builder = make_builder(target, &info, &struct_name, module);
+            module.builders.push(builder);
+        }
+    }
+
+    let mut scope = Scope::new();
+    for (module_name, op_module) in modules {
+        let module = scope.new_module(module_name);
+        module
+            .vis("pub")
+            .import("crate::error", "Error")
+            .import("crate::error", "Result")
+            .import("crate::operator", "Operator")
+            .import(target.module_path(), target.type_name());
+        for name in op_module.imports {
+            let path = if name == "DType" {
+                "crate::ndarray"
+            } else {
+                "crate::operator"
+            };
+            module.import(path, name);
+        }
+        if !op_module.enums.is_empty() {
+            module.import("std", "fmt");
+        }
+
+        for (name, values) in &op_module.enums {
+            let (param_enum, display) = make_enum(name, values);
+            module.push_enum(param_enum);
+            module.push_impl(display);
+        }
+        for (structs, impls) in op_module.builders {
+            for s in structs {
+                module.push_struct(s);
+            }
+            for imp in impls {
+                module.push_impl(imp);
+            }
+        }
+    }
+
+    fs::write(path, scope.to_string()).expect("failed to write op module");
+}
+
+fn list_all_op_names() -> Vec<String> {
+    let mut size = 0;
+    let mut names = ptr::null_mut();
+    check_call!(MXListAllOpNames(&mut size, &mut names));
+    let mut names = to_string_vec(size, names as *const *const c_char);
+    // Sorted so the output is stable and aliases resolve the same way.
+    names.sort();
+    names
+}
+
+fn to_string_vec(size: u32, array: *const *const c_char) -> Vec<String> {
+    if size == 0 {
+        return Vec::new();
+    }
+    unsafe { slice::from_raw_parts(array, size as usize) }
+        .iter()
+        .map(|s| unsafe { CStr::from_ptr(*s) }.to_string_lossy().into_owned())
+        .collect()
+}
+
+fn optional_string(s: *const c_char) -> String {
+    if s.is_null() {
+        String::new()
+    } else {
+        unsafe { CStr::from_ptr(s) }.to_string_lossy().into_owned()
+    }
+}
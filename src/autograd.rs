@@ -1,6 +1,11 @@
+use crate::error::{Error, ErrorKind, Result};
+use crate::ndarray::{GradReq, NDArray};
+use crate::operator::GetHandle;
 use mxnet_sys::{
-    MXAutogradIsRecording, MXAutogradIsTraining, MXAutogradSetIsRecording, MXAutogradSetIsTraining,
+    MXAutogradBackwardEx, MXAutogradIsRecording, MXAutogradIsTraining, MXAutogradMarkVariables,
+    MXAutogradSetIsRecording, MXAutogradSetIsTraining, NDArrayHandle,
 };
+use std::ptr;
 
 pub struct RecordingStateScope {
     enter_is_record: Option<bool>,
@@ -11,6 +16,13 @@ pub struct RecordingStateScope {
 
 impl RecordingStateScope {
     pub fn new(is_record: Option<bool>, train_mode: Option<bool>) -> RecordingStateScope {
+        RecordingStateScope::try_new(is_record, train_mode).unwrap()
+    }
+
+    pub fn try_new(
+        is_record: Option<bool>,
+        train_mode: Option<bool>,
+    ) -> Result<RecordingStateScope> {
         let mut state = RecordingStateScope {
             enter_is_record: is_record,
             enter_train_mode: train_mode,
@@ -18,18 +30,24 @@ impl RecordingStateScope {
             prev_train_mode: None,
         };
 
-        state.enter();
-        state
+        state.try_enter()?;
+        Ok(state)
     }
 
     pub fn enter(&mut self) {
+        self.try_enter().unwrap()
+    }
+
+    pub fn try_enter(&mut self) -> Result<()> {
         if let Some(enter_is_record) = self.enter_is_record {
-            self.prev_is_record = Some(set_recording(enter_is_record));
+            self.prev_is_record = Some(try_set_recording(enter_is_record)?);
         }
 
         if let Some(enter_train_mode) = self.enter_train_mode {
-            self.prev_train_mode = Some(set_training(enter_train_mode));
+            self.prev_train_mode = Some(try_set_training(enter_train_mode)?);
         }
+
+        Ok(())
     }
 }
 
@@ -46,27 +64,43 @@ impl Drop for RecordingStateScope {
 }
 
 fn set_recording(is_recording: bool) -> bool {
+    try_set_recording(is_recording).unwrap()
+}
+
+fn try_set_recording(is_recording: bool) -> Result<bool> {
     let mut prev = 0;
-    check_call!(MXAutogradSetIsRecording(is_recording as i32, &mut prev));
-    prev != 0
+    try_call!(MXAutogradSetIsRecording(is_recording as i32, &mut prev))?;
+    Ok(prev != 0)
 }
 
 fn set_training(train_mode: bool) -> bool {
+    try_set_training(train_mode).unwrap()
+}
+
+fn try_set_training(train_mode: bool) -> Result<bool> {
     let mut prev = 0;
-    check_call!(MXAutogradSetIsTraining(train_mode as i32, &mut prev));
-    prev != 0
+    try_call!(MXAutogradSetIsTraining(train_mode as i32, &mut prev))?;
+    Ok(prev != 0)
+}
+
+pub fn is_recording() -> bool {
+    try_is_recording().unwrap()
 }
 
-fn is_recording() -> bool {
+pub fn try_is_recording() -> Result<bool> {
     let mut curr = false;
-    check_call!(MXAutogradIsRecording(&mut curr));
-    curr
+    try_call!(MXAutogradIsRecording(&mut curr))?;
+    Ok(curr)
 }
 
-fn is_training() -> bool {
+pub fn is_training() -> bool {
+    try_is_training().unwrap()
+}
+
+pub fn try_is_training() -> Result<bool> {
     let mut curr = false;
-    check_call!(MXAutogradIsTraining(&mut curr));
-    curr
+    try_call!(MXAutogradIsTraining(&mut curr))?;
+    Ok(curr)
 }
 
 /// Returns an autograd recording scope context to be used in 'with' statement
@@ -89,6 +123,91 @@ pub fn predict_mode() -> RecordingStateScope {
     RecordingStateScope::new(None, Some(false))
 }
 
+/// Mark NDArrays as variables to compute gradient for autograd.
+pub fn mark_variables(variables: &[&NDArray], gradients: &[&NDArray], grad_reqs: &[GradReq]) {
+    try_mark_variables(variables, gradients, grad_reqs).unwrap()
+}
+
+pub fn try_mark_variables(
+    variables: &[&NDArray],
+    gradients: &[&NDArray],
+    grad_reqs: &[GradReq],
+) -> Result<()> {
+    if variables.len() != gradients.len() || variables.len() != grad_reqs.len() {
+        return Err(Error::new(
+            ErrorKind::InvalidParameter,
+            format!(
+                "got {} variables, {} gradients and {} grad_reqs",
+                variables.len(),
+                gradients.len(),
+                grad_reqs.len()
+            ),
+        ));
+    }
+
+    let mut variable_handles: Vec<NDArrayHandle> = variables.iter().map(|v| v.handle()).collect();
+    let mut gradient_handles: Vec<NDArrayHandle> = gradients.iter().map(|g| g.handle()).collect();
+    let mut reqs: Vec<u32> = grad_reqs.iter().map(|req| *req as u32).collect();
+
+    try_call!(MXAutogradMarkVariables(
+        variable_handles.len() as u32,
+        variable_handles.as_mut_ptr(),
+        reqs.as_mut_ptr(),
+        gradient_handles.as_mut_ptr()
+    ))
+}
+
+/// Compute the gradients of heads w.r.t previously marked variables.
+///
+/// `head_grads` defaults to ones when `None`.
+pub fn backward(
+    heads: &[&NDArray],
+    head_grads: Option<&[&NDArray]>,
+    retain_graph: bool,
+    train_mode: bool,
+) {
+    try_backward(heads, head_grads, retain_graph, train_mode).unwrap()
+}
+
+pub fn try_backward(
+    heads: &[&NDArray],
+    head_grads: Option<&[&NDArray]>,
+    retain_graph: bool,
+    train_mode: bool,
+) -> Result<()> {
+    let mut head_handles: Vec<NDArrayHandle> = heads.iter().map(|h| h.handle()).collect();
+
+    let mut ograd_handles: Vec<NDArrayHandle> = match head_grads {
+        Some(head_grads) => {
+            if head_grads.len() != heads.len() {
+                return Err(Error::new(
+                    ErrorKind::InvalidParameter,
+                    format!(
+                        "heads and head_grads must have the same length, got {} and {}",
+                        heads.len(),
+                        head_grads.len()
+                    ),
+                ));
+            }
+            head_grads.iter().map(|g| g.handle()).collect()
+        }
+        None => vec![ptr::null_mut(); heads.len()],
+    };
+
+    try_call!(MXAutogradBackwardEx(
+        head_handles.len() as u32,
+        head_handles.as_mut_ptr(),
+        ograd_handles.as_mut_ptr(),
+        0,
+        ptr::null_mut(),
+        retain_graph as i32,
+        0,
+        train_mode as i32,
+        ptr::null_mut(),
+        ptr::null_mut()
+    ))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -105,4 +224,20 @@ mod tests {
 
         println!("{}", x);
     }
+
+    #[test]
+    fn backward_square() {
+        let x = ndarray::NDArray::builder()
+            .data(&[1.0f32, 2.0, 3.0])
+            .create();
+        x.attach_grad();
+        let y = {
+            let _scope = record();
+            x.clone() * x.clone()
+        };
+        y.backward(None, false, true);
+        let grad = x.grad();
+        grad.wait_to_read();
+        assert_eq!(grad.data::<f32>(), &[2.0, 4.0, 6.0]);
+    }
 }
@@ -1,21 +1,95 @@
+use crate::error::{Error, ErrorKind, Result};
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::path::Path;
+use std::slice;
+
 pub const OP_NAME_PREFIX_LIST: &[&'static str; 5] =
     &["_contrib_", "_linalg_", "_sparse_", "_image_", "_random_"];
 
+/// Copy a `const char**` array returned by MXNet into owned Strings.
+pub(crate) fn to_string_vec(size: u32, array: *const *const c_char) -> Vec<String> {
+    if size == 0 {
+        return Vec::new();
+    }
+    unsafe { slice::from_raw_parts(array, size as usize) }
+        .iter()
+        .map(|s| unsafe { CStr::from_ptr(*s) }.to_string_lossy().into_owned())
+        .collect()
+}
+
+pub(crate) fn path_to_cstring(path: &Path) -> Result<CString> {
+    let path = path.to_str().ok_or_else(|| {
+        Error::new(
+            ErrorKind::InvalidParameter,
+            format!("path is not valid unicode: {:?}", path),
+        )
+    })?;
+    Ok(CString::new(path)?)
+}
+
+/// Name of the C function called by the expression `call`, e.g.
+/// `MXNDArrayFree` for `mxnet_sys::MXNDArrayFree(handle)`.
+#[doc(hidden)]
+pub fn call_name(call: &'static str) -> &'static str {
+    let func = call.split('(').next().unwrap_or(call);
+    func.rsplit("::").next().unwrap_or(func).trim()
+}
+
+/// Call an MXNet C function and turn a non-zero return code into
+/// `Err(error::Error)` carrying the message from `MXGetLastError`.
+///
+/// Takes a bare function name, looked up in `mxnet_sys`, or any expression
+/// returning the code, like `mxnet_sys::MXNDArrayFree(handle)`.
+#[macro_export]
+macro_rules! try_call {
+    ($func:ident($($arg:expr),* $(,)*)) => {
+        if unsafe { $crate::mxnet_sys::$func($($arg),*) } != 0 {
+            Err($crate::error::Error::last(stringify!($func)))
+        } else {
+            Ok(())
+        }
+    };
+    ($mx_call:expr) => {
+        if unsafe { $mx_call } != 0 {
+            Err($crate::error::Error::last($crate::base::call_name(
+                stringify!($mx_call),
+            )))
+        } else {
+            Ok(())
+        }
+    };
+}
+
+/// Like `try_call!`, but panics with the MXNet message.
 #[macro_export]
 macro_rules! check_call {
+    ($func:ident($($arg:expr),* $(,)*)) => {
+        if let Err(err) = $crate::try_call!($func($($arg),*)) {
+            panic!("{}", err);
+        }
+    };
     ($mx_call:expr) => {
-        unsafe {
-            if $mx_call != 0 {
-                panic!(std::ffi::CStr::from_ptr(mxnet_sys::MXGetLastError())
-                    .to_str()
-                    .unwrap());
-            }
+        if let Err(err) = $crate::try_call!($mx_call) {
+            panic!("{}", err);
         }
     };
 }
 
 #[cfg(test)]
 mod tests {
-    // use super::*;
+    use super::*;
 
+    #[test]
+    fn call_names() {
+        assert_eq!(call_name("MXNDArrayFree(handle)"), "MXNDArrayFree");
+        assert_eq!(
+            call_name("mxnet_sys :: MXNDArrayFree(handle)"),
+            "MXNDArrayFree"
+        );
+        let mut version = 0;
+        try_call!(mxnet_sys::MXGetVersion(&mut version)).unwrap();
+        check_call!(MXGetVersion(&mut version));
+        assert!(version > 0);
+    }
 }
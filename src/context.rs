@@ -1,4 +1,6 @@
+use crate::error::Result;
 use mxnet_sys::{MXGetGPUCount, MXGetGPUMemoryInformation64};
+use std::fmt;
 
 pub use DeviceType::*;
 
@@ -22,7 +24,7 @@ impl From<i32> for DeviceType {
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Context {
     device_type: DeviceType,
     device_id: i32,
@@ -45,6 +47,19 @@ impl Context {
     }
 }
 
+/// Formats like `cpu(0)`, as MXNet takes it for the `ctx` param.
+impl fmt::Display for Context {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let device = match self.device_type {
+            CPU => "cpu",
+            GPU => "gpu",
+            CPUPinned => "cpu_pinned",
+            CPUShared => "cpu_shared",
+        };
+        write!(f, "{}({})", device, self.device_id)
+    }
+}
+
 impl Default for Context {
     fn default() -> Context {
         Context::new(CPU, 0)
@@ -64,16 +79,25 @@ pub fn cpu_pinned() -> Context {
 }
 
 pub fn num_gpus() -> usize {
+    try_num_gpus().unwrap()
+}
+
+pub fn try_num_gpus() -> Result<usize> {
     let mut count = 0;
-    check_call!(MXGetGPUCount(&mut count));
-    count as usize
+    try_call!(MXGetGPUCount(&mut count))?;
+    Ok(count as usize)
 }
 
+/// Returns `(free, total)` memory of the gpu in bytes.
 pub fn gpu_memory_info(device_id: i32) -> (u64, u64) {
+    try_gpu_memory_info(device_id).unwrap()
+}
+
+pub fn try_gpu_memory_info(device_id: i32) -> Result<(u64, u64)> {
     let mut free = 0;
     let mut total = 0;
-    check_call!(MXGetGPUMemoryInformation64(
+    try_call!(MXGetGPUMemoryInformation64(
         device_id, &mut free, &mut total
-    ));
-    (free, total)
+    ))?;
+    Ok((free, total))
 }
@@ -1 +1,150 @@
+use mxnet_sys::MXGetLastError;
+use std::error;
+use std::ffi::{CStr, NulError};
+use std::fmt;
+use std::io;
+use std::result;
 
+pub type Result<T> = result::Result<T, Error>;
+
+/// Rough category of a failure, parsed from the message MXNet reports.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ErrorKind {
+    ShapeMismatch,
+    TypeMismatch,
+    UnknownOperator,
+    InvalidParameter,
+    OutOfMemory,
+    Io,
+    InvalidFormat,
+    Other,
+}
+
+impl ErrorKind {
+    /// Guess the category from an `MXGetLastError` message.
+    pub fn parse(message: &str) -> ErrorKind {
+        let message = message.to_lowercase();
+        let contains_any = |patterns: &[&str]| patterns.iter().any(|p| message.contains(p));
+
+        if contains_any(&["out of memory", "failed to allocate", "bad_alloc"]) {
+            ErrorKind::OutOfMemory
+        } else if contains_any(&[
+            "cannot find operator",
+            "not registered",
+            "cannot find op",
+            "unknown op",
+        ]) {
+            ErrorKind::UnknownOperator
+        } else if contains_any(&["shape inconsistent", "shape mismatch", "incompatible shape"])
+            || (message.contains("shape") && message.contains("check failed"))
+        {
+            ErrorKind::ShapeMismatch
+        } else if contains_any(&["cannot find argument", "invalid parameter", "invalid param"]) {
+            ErrorKind::InvalidParameter
+        } else if contains_any(&["type inconsistent", "dtype", "type mismatch"]) {
+            ErrorKind::TypeMismatch
+        } else {
+            ErrorKind::Other
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Error {
+    kind: ErrorKind,
+    message: String,
+    call: Option<&'static str>,
+}
+
+impl Error {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Error {
+        Error {
+            kind,
+            message: message.into(),
+            call: None,
+        }
+    }
+
+    /// Build an error from `MXGetLastError` after `call` returned non-zero.
+    pub fn last(call: &'static str) -> Error {
+        let message = unsafe { CStr::from_ptr(MXGetLastError()) }
+            .to_string_lossy()
+            .into_owned();
+        Error {
+            kind: ErrorKind::parse(&message),
+            message,
+            call: Some(call),
+        }
+    }
+
+    /// Prepend some context to the message, keeping kind and call.
+    pub(crate) fn context(mut self, context: impl fmt::Display) -> Error {
+        self.message = format!("{}\n{}", context, self.message);
+        self
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// The C function that failed, if the error came from MXNet.
+    pub fn call(&self) -> Option<&'static str> {
+        self.call
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.call {
+            Some(call) => write!(f, "{} failed ({:?}): {}", call, self.kind, self.message),
+            None => write!(f, "{:?}: {}", self.kind, self.message),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        Error::new(ErrorKind::Io, err.to_string())
+    }
+}
+
+// Strings passed to MXNet can't contain NUL.
+impl From<NulError> for Error {
+    fn from(err: NulError) -> Error {
+        Error::new(ErrorKind::InvalidParameter, err.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_error_kind() {
+        assert_eq!(
+            ErrorKind::parse(
+                "Error in operator _plus: Shape inconsistent, Provided = [2], inferred shape=[3]"
+            ),
+            ErrorKind::ShapeMismatch
+        );
+        assert_eq!(
+            ErrorKind::parse("Cannot find operator foo"),
+            ErrorKind::UnknownOperator
+        );
+        assert_eq!(
+            ErrorKind::parse("cudaMalloc failed: out of memory"),
+            ErrorKind::OutOfMemory
+        );
+        assert_eq!(
+            ErrorKind::parse("Cannot find argument 'foo', Possible Arguments: ..."),
+            ErrorKind::InvalidParameter
+        );
+        assert_eq!(ErrorKind::parse("something else"), ErrorKind::Other);
+    }
+}
@@ -0,0 +1,511 @@
+use crate::context::Context;
+use crate::error::{Error, ErrorKind, Result};
+use crate::ndarray::{DType, GradReq, NDArray};
+use crate::operator::GetHandle;
+use crate::symbol::Symbol;
+use mxnet_sys::*;
+use std::collections::HashMap;
+use std::ptr;
+use std::slice;
+
+/// A symbol bound to argument, gradient and auxiliary state arrays.
+pub struct Executor {
+    handle: ExecutorHandle,
+    symbol: Symbol,
+    context: Context,
+    arg_arrays: Vec<NDArray>,
+    grad_arrays: Vec<Option<NDArray>>,
+    grad_reqs: Vec<GradReq>,
+    aux_arrays: Vec<NDArray>,
+}
+
+impl Symbol {
+    /// Bind the arrays to the symbol, returns an executor running on `ctx`.
+    ///
+    /// `args` and `aux_states` follow the order of `list_arguments` and
+    /// `list_auxiliary_states`. Without `args_grad` no gradient is computed.
+    /// `grad_reqs` holds one request per argument, or a single one for all.
+    pub fn bind(
+        &self,
+        ctx: Context,
+        args: &[NDArray],
+        args_grad: Option<&[NDArray]>,
+        grad_reqs: &[GradReq],
+        aux_states: &[NDArray],
+    ) -> Executor {
+        self.try_bind(ctx, args, args_grad, grad_reqs, aux_states)
+            .unwrap()
+    }
+
+    pub fn try_bind(
+        &self,
+        ctx: Context,
+        args: &[NDArray],
+        args_grad: Option<&[NDArray]>,
+        grad_reqs: &[GradReq],
+        aux_states: &[NDArray],
+    ) -> Result<Executor> {
+        let grad_arrays = match args_grad {
+            Some(args_grad) => args_grad.iter().cloned().map(Some).collect(),
+            None => vec![None; args.len()],
+        };
+        let grad_reqs = match grad_reqs.len() {
+            1 => vec![grad_reqs[0]; args.len()],
+            _ => grad_reqs.to_vec(),
+        };
+        Executor::try_new(
+            self,
+            ctx,
+            args.to_vec(),
+            grad_arrays,
+            &grad_reqs,
+            aux_states.to_vec(),
+            None,
+        )
+    }
+
+    /// Bind with float32 arrays allocated from the shapes inferred from
+    /// `input_shapes`.
+    ///
+    /// Gradient buffers are only allocated when `grad_req` is not `Null`.
+    pub fn simple_bind(
+        &self,
+        ctx: Context,
+        input_shapes: &HashMap<&str, &[u32]>,
+        grad_req: GradReq,
+    ) -> Executor {
+        self.try_simple_bind(ctx, input_shapes, grad_req).unwrap()
+    }
+
+    pub fn try_simple_bind(
+        &self,
+        ctx: Context,
+        input_shapes: &HashMap<&str, &[u32]>,
+        grad_req: GradReq,
+    ) -> Result<Executor> {
+        self.try_simple_bind_with_types(ctx, input_shapes, &HashMap::new(), grad_req)
+    }
+
+    /// Like `simple_bind`, with the types inferred from `input_types`.
+    /// Arguments whose type can't be inferred are float32.
+    pub fn simple_bind_with_types(
+        &self,
+        ctx: Context,
+        input_shapes: &HashMap<&str, &[u32]>,
+        input_types: &HashMap<&str, DType>,
+        grad_req: GradReq,
+    ) -> Executor {
+        self.try_simple_bind_with_types(ctx, input_shapes, input_types, grad_req)
+            .unwrap()
+    }
+
+    pub fn try_simple_bind_with_types(
+        &self,
+        ctx: Context,
+        input_shapes: &HashMap<&str, &[u32]>,
+        input_types: &HashMap<&str, DType>,
+        grad_req: GradReq,
+    ) -> Result<Executor> {
+        let (arg_shapes, _, aux_shapes) = self.try_infer_shape(input_shapes)?;
+        let arg_names = self.try_list_arguments()?;
+        let mut known_types: HashMap<&str, DType> = input_types
+            .iter()
+            .map(|(name, dtype)| (*name, *dtype))
+            .collect();
+        let inferred = if known_types.is_empty() {
+            vec![DType::None; arg_names.len()]
+        } else {
+            (self.infer_type_impl(&known_types)?.0).0
+        };
+        for (name, dtype) in arg_names.iter().zip(inferred) {
+            if dtype == DType::None {
+                known_types.insert(name, DType::F32);
+            }
+        }
+        let (arg_types, _, aux_types) = self.try_infer_type(&known_types)?;
+
+        let alloc = |shape: &[u32], dtype: DType| {
+            NDArray::builder()
+                .shape(shape)
+                .context(ctx)
+                .dtype(dtype)
+                .delay_alloc(false)
+                .try_create()
+        };
+
+        let mut arg_arrays = Vec::with_capacity(arg_shapes.len());
+        let mut grad_arrays = Vec::with_capacity(arg_shapes.len());
+        for (shape, dtype) in arg_shapes.iter().zip(arg_types) {
+            arg_arrays.push(alloc(shape, dtype)?);
+            grad_arrays.push(match grad_req {
+                GradReq::Null => None,
+                _ => Some(alloc(shape, dtype)?),
+            });
+        }
+        let aux_arrays = aux_shapes
+            .iter()
+            .zip(aux_types)
+            .map(|(shape, dtype)| alloc(shape, dtype))
+            .collect::<Result<Vec<_>>>()?;
+
+        Executor::try_new(
+            self,
+            ctx,
+            arg_arrays,
+            grad_arrays,
+            &vec![grad_req; arg_names.len()],
+            aux_arrays,
+            None,
+        )
+    }
+}
+
+impl Executor {
+    pub(crate) fn try_new(
+        symbol: &Symbol,
+        ctx: Context,
+        arg_arrays: Vec<NDArray>,
+        grad_arrays: Vec<Option<NDArray>>,
+        grad_reqs: &[GradReq],
+        aux_arrays: Vec<NDArray>,
+        shared_exec: Option<&Executor>,
+    ) -> Result<Executor> {
+        let num_args = symbol.try_list_arguments()?.len();
+        let num_aux = symbol.try_list_auxiliary_states()?.len();
+        if arg_arrays.len() != num_args
+            || grad_arrays.len() != num_args
+            || grad_reqs.len() != num_args
+        {
+            return Err(Error::new(
+                ErrorKind::InvalidParameter,
+                format!(
+                    "symbol has {} arguments, got {} args, {} args_grad and {} grad_reqs",
+                    num_args,
+                    arg_arrays.len(),
+                    grad_arrays.len(),
+                    grad_reqs.len()
+                ),
+            ));
+        }
+        if aux_arrays.len() != num_aux {
+            return Err(Error::new(
+                ErrorKind::InvalidParameter,
+                format!(
+                    "symbol has {} auxiliary states, got {}",
+                    num_aux,
+                    aux_arrays.len()
+                ),
+            ));
+        }
+
+        let mut arg_handles = arg_arrays.iter().map(|a| a.handle()).collect::<Vec<_>>();
+        let mut grad_handles = grad_arrays
+            .iter()
+            .map(|g| g.as_ref().map_or(ptr::null_mut(), |g| g.handle()))
+            .collect::<Vec<_>>();
+        // Arguments without a gradient buffer can't be written to.
+        let mut reqs = grad_arrays
+            .iter()
+            .zip(grad_reqs)
+            .map(|(g, req)| match g {
+                Some(_) => *req as u32,
+                None => GradReq::Null as u32,
+            })
+            .collect::<Vec<_>>();
+        let mut aux_handles = aux_arrays.iter().map(|a| a.handle()).collect::<Vec<_>>();
+        let shared_handle = shared_exec.map_or(ptr::null_mut(), |e| e.handle);
+
+        let mut handle = ptr::null_mut();
+        try_call!(MXExecutorBindEX(
+            symbol.handle(),
+            ctx.device_type() as i32,
+            ctx.device_id(),
+            0,
+            ptr::null_mut(),
+            ptr::null_mut(),
+            ptr::null_mut(),
+            arg_handles.len() as u32,
+            arg_handles.as_mut_ptr(),
+            grad_handles.as_mut_ptr(),
+            reqs.as_mut_ptr(),
+            aux_handles.len() as u32,
+            aux_handles.as_mut_ptr(),
+            shared_handle,
+            &mut handle
+        ))?;
+
+        Ok(Executor {
+            handle,
+            symbol: symbol.clone(),
+            context: ctx,
+            arg_arrays,
+            grad_arrays,
+            grad_reqs: grad_reqs.to_vec(),
+            aux_arrays,
+        })
+    }
+
+    /// A new executor for new input shapes, e.g. another batch size,
+    /// sharing memory with this one.
+    ///
+    /// Arrays that shrink become views of the current ones. Growing arrays
+    /// are reallocated when `allow_up_sizing`, otherwise an error is returned.
+    pub fn reshape(&self, input_shapes: &HashMap<&str, &[u32]>, allow_up_sizing: bool) -> Executor {
+        self.try_reshape(input_shapes, allow_up_sizing).unwrap()
+    }
+
+    pub fn try_reshape(
+        &self,
+        input_shapes: &HashMap<&str, &[u32]>,
+        allow_up_sizing: bool,
+    ) -> Result<Executor> {
+        let (arg_shapes, _, aux_shapes) = self.symbol.try_infer_shape(input_shapes)?;
+        let arg_names = self.symbol.try_list_arguments()?;
+        let aux_names = self.symbol.try_list_auxiliary_states()?;
+
+        let reshape = |name: &str, array: &NDArray, new_shape: &[u32]| -> Result<NDArray> {
+            let shape = array.try_shape()?;
+            let size = |shape: &[u32]| shape.iter().map(|s| *s as u64).product::<u64>();
+            if shape == new_shape {
+                Ok(array.clone())
+            } else if size(new_shape) <= size(&shape) {
+                let new_shape = new_shape.iter().map(|s| *s as i64).collect::<Vec<_>>();
+                array.try_reshape_view(&new_shape)
+            } else if allow_up_sizing {
+                NDArray::builder()
+                    .shape(new_shape)
+                    .context(array.try_context()?)
+                    .dtype(array.try_dtype()?)
+                    .delay_alloc(false)
+                    .try_create()
+            } else {
+                Err(Error::new(
+                    ErrorKind::ShapeMismatch,
+                    format!(
+                        "new shape of {} {:?} is larger than {:?}, set allow_up_sizing to reshape",
+                        name, new_shape, shape
+                    ),
+                ))
+            }
+        };
+
+        let mut arg_arrays = Vec::with_capacity(arg_shapes.len());
+        let mut grad_arrays = Vec::with_capacity(arg_shapes.len());
+        for (i, new_shape) in arg_shapes.iter().enumerate() {
+            arg_arrays.push(reshape(&arg_names[i], &self.arg_arrays[i], new_shape)?);
+            grad_arrays.push(match &self.grad_arrays[i] {
+                Some(grad) => Some(reshape(&arg_names[i], grad, new_shape)?),
+                None => None,
+            });
+        }
+        let aux_arrays = aux_shapes
+            .iter()
+            .enumerate()
+            .map(|(i, new_shape)| reshape(&aux_names[i], &self.aux_arrays[i], new_shape))
+            .collect::<Result<Vec<_>>>()?;
+
+        Executor::try_new(
+            &self.symbol,
+            self.context,
+            arg_arrays,
+            grad_arrays,
+            &self.grad_reqs,
+            aux_arrays,
+            Some(self),
+        )
+    }
+
+    pub fn forward(&mut self, is_train: bool) -> &mut Self {
+        self.try_forward(is_train).unwrap()
+    }
+
+    pub fn try_forward(&mut self, is_train: bool) -> Result<&mut Self> {
+        try_call!(MXExecutorForward(self.handle, is_train as i32))?;
+        Ok(self)
+    }
+
+    /// `head_grads` may be empty when the outputs are losses.
+    pub fn backward(&mut self, head_grads: &[NDArray]) -> &mut Self {
+        self.try_backward(head_grads).unwrap()
+    }
+
+    pub fn try_backward(&mut self, head_grads: &[NDArray]) -> Result<&mut Self> {
+        let mut handles = head_grads.iter().map(|g| g.handle()).collect::<Vec<_>>();
+        try_call!(MXExecutorBackward(
+            self.handle,
+            handles.len() as u32,
+            handles.as_mut_ptr()
+        ))?;
+        Ok(self)
+    }
+
+    pub fn outputs(&self) -> Vec<NDArray> {
+        self.try_outputs().unwrap()
+    }
+
+    pub fn try_outputs(&self) -> Result<Vec<NDArray>> {
+        let mut size = 0;
+        let mut handles = ptr::null_mut();
+        try_call!(MXExecutorOutputs(self.handle, &mut size, &mut handles))?;
+        if size == 0 {
+            return Ok(Vec::new());
+        }
+        Ok(unsafe { slice::from_raw_parts(handles, size as usize) }
+            .iter()
+            .map(|handle| NDArray::from(*handle))
+            .collect())
+    }
+
+    pub fn symbol(&self) -> &Symbol {
+        &self.symbol
+    }
+
+    pub fn context(&self) -> Context {
+        self.context
+    }
+
+    pub fn arg_arrays(&self) -> &[NDArray] {
+        &self.arg_arrays
+    }
+
+    pub fn grad_arrays(&self) -> &[Option<NDArray>] {
+        &self.grad_arrays
+    }
+
+    pub fn aux_arrays(&self) -> &[NDArray] {
+        &self.aux_arrays
+    }
+
+    pub fn arg_dict(&self) -> HashMap<String, NDArray> {
+        self.symbol
+            .list_arguments()
+            .into_iter()
+            .zip(self.arg_arrays.iter().cloned())
+            .collect()
+    }
+
+    /// Only arguments bound with a gradient buffer are included.
+    pub fn grad_dict(&self) -> HashMap<String, NDArray> {
+        self.symbol
+            .list_arguments()
+            .into_iter()
+            .zip(self.grad_arrays.iter().cloned())
+            .filter_map(|(name, grad)| grad.map(|grad| (name, grad)))
+            .collect()
+    }
+
+    pub fn aux_dict(&self) -> HashMap<String, NDArray> {
+        self.symbol
+            .list_auxiliary_states()
+            .into_iter()
+            .zip(self.aux_arrays.iter().cloned())
+            .collect()
+    }
+}
+
+impl Drop for Executor {
+    fn drop(&mut self) {
+        // Panicking in drop could abort while unwinding, and there's nothing
+        // left to do with the executor anyway.
+        let _ = try_call!(MXExecutorFree(self.handle));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context;
+    use crate::operator::Operator;
+
+    #[test]
+    fn bind_forward_backward() {
+        let data = Symbol::new("data");
+        let fc = Operator::new("FullyConnected")
+            .set_input("data", &data)
+            .set_param("num_hidden", &2)
+            .create_symbol(Some("fc1"));
+
+        let args = vec![
+            NDArray::builder()
+                .data(&[1.0f32, 2.0, 3.0])
+                .shape(&[1, 3])
+                .create(),
+            NDArray::builder()
+                .data(&[1.0f32, 1.0, 1.0, 0.0, 1.0, 0.0])
+                .shape(&[2, 3])
+                .create(),
+            NDArray::builder().data(&[0.5f32, 0.0]).create(),
+        ];
+        let grads = vec![
+            NDArray::builder().shape(&[1, 3]).create(),
+            NDArray::builder().shape(&[2, 3]).create(),
+            NDArray::builder().shape(&[2]).create(),
+        ];
+
+        let mut exec = fc.bind(context::cpu(), &args, Some(&grads), &[GradReq::Write], &[]);
+        exec.forward(true);
+        assert_eq!(exec.outputs()[0].to_vec::<f32>(), vec![6.5, 2.0]);
+
+        let head_grad = NDArray::builder()
+            .data(&[1.0f32, 1.0])
+            .shape(&[1, 2])
+            .create();
+        exec.backward(&[head_grad]);
+        let grad_dict = exec.grad_dict();
+        assert_eq!(
+            grad_dict["fc1_weight"].to_vec::<f32>(),
+            vec![1.0, 2.0, 3.0, 1.0, 2.0, 3.0]
+        );
+        assert_eq!(grad_dict["fc1_bias"].to_vec::<f32>(), vec![1.0, 1.0]);
+        assert_eq!(exec.arg_dict().len(), 3);
+        assert!(exec.aux_dict().is_empty());
+    }
+
+    #[test]
+    fn simple_bind_and_reshape() {
+        let data = Symbol::new("data");
+        let fc = Operator::new("FullyConnected")
+            .set_input("data", &data)
+            .set_param("num_hidden", &10)
+            .create_symbol(Some("fc1"));
+
+        let mut input_shapes = HashMap::new();
+        input_shapes.insert("data", &[4u32, 5][..]);
+        let mut exec = fc.simple_bind(context::cpu(), &input_shapes, GradReq::Write);
+        assert_eq!(exec.arg_dict()["fc1_weight"].shape(), vec![10, 5]);
+        assert_eq!(exec.arg_dict()["fc1_weight"].dtype(), DType::F32);
+        assert_eq!(exec.grad_dict().len(), 3);
+        exec.forward(false);
+        assert_eq!(exec.outputs()[0].shape(), vec![4, 10]);
+
+        input_shapes.insert("data", &[2u32, 5][..]);
+        let mut smaller = exec.reshape(&input_shapes, false);
+        smaller.forward(false);
+        assert_eq!(smaller.outputs()[0].shape(), vec![2, 10]);
+
+        input_shapes.insert("data", &[8u32, 5][..]);
+        assert!(exec.try_reshape(&input_shapes, false).is_err());
+        let mut larger = exec.reshape(&input_shapes, true);
+        larger.forward(false);
+        assert_eq!(larger.outputs()[0].shape(), vec![8, 10]);
+    }
+
+    #[test]
+    fn simple_bind_with_types() {
+        let data = Symbol::new("data");
+        let fc = Operator::new("FullyConnected")
+            .set_input("data", &data)
+            .set_param("num_hidden", &10)
+            .create_symbol(Some("fc1"));
+
+        let mut input_shapes = HashMap::new();
+        input_shapes.insert("data", &[4u32, 5][..]);
+        let mut input_types = HashMap::new();
+        input_types.insert("data", DType::F64);
+        let exec =
+            fc.simple_bind_with_types(context::cpu(), &input_shapes, &input_types, GradReq::Null);
+        let args = exec.arg_dict();
+        assert_eq!(args["data"].dtype(), DType::F64);
+        assert_eq!(args["fc1_weight"].dtype(), DType::F64);
+    }
+}
@@ -4,9 +4,14 @@ extern crate enum_str_derive;
 #[macro_use]
 pub mod base;
 
+// For the `try_call!` and `check_call!` macros.
+#[doc(hidden)]
+pub use mxnet_sys;
+
 pub mod autograd;
 pub mod context;
 pub mod error;
+pub mod executor;
 pub mod ndarray;
 pub mod op_map;
 pub mod operator;
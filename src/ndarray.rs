@@ -1,6 +1,23 @@
+pub mod format;
+mod index;
+mod math;
+mod reduce;
 pub mod register;
-
+mod shape;
+pub mod utils;
+
+pub use self::index::{NewAxis, SliceElem};
+pub use self::reduce::Axes;
+pub use self::utils::{
+    add_n, arange, concat, empty, eye, full, full_like, linspace, load, load_from_buffer, ones,
+    ones_like, save, save_list, stack, try_add_n, try_arange, try_concat, try_empty, try_eye,
+    try_full, try_full_like, try_linspace, try_load, try_load_from_buffer, try_ones, try_ones_like,
+    try_save, try_save_list, try_stack, try_zeros, try_zeros_like, zeros, zeros_like,
+};
+
+use crate::autograd;
 use crate::context::{Context, DeviceType};
+use crate::error::{Error, ErrorKind, Result};
 use crate::operator::{GetHandle, Operator};
 // use mxnet_sys::{
 //     MXNDArrayCreate, MXNDArrayCreateNone, MXNDArrayFree, MXNDArrayGetContext, MXNDArrayGetDType,
@@ -8,28 +25,99 @@ use crate::operator::{GetHandle, Operator};
 //     MXNDArraySyncCopyFromCPU, MXNDArrayWaitAll, MXNDArrayWaitToRead, MXNDArrayWaitToWrite,
 //     NDArrayHandle,
 // };
+use half::f16;
 use mxnet_sys::*;
-use ndarray::{ArrayView, Dim, ShapeBuilder};
+use ndarray::{Array, ArrayBase, ArrayView, Data, Dim, Dimension, IxDyn};
+use std::convert::TryFrom;
 use std::ffi::c_void;
 use std::fmt;
 use std::mem;
 use std::rc::Rc;
 use std::{ptr, slice};
 
-// Implement add, sub, mul, div, mod for NDAarry and f32.
+// Implement add, sub, mul, div, mod for NDArray and f32, owned or borrowed.
+// Arrays always go through the `broadcast_*` op, which also takes equal
+// shapes, so the operators don't need to look at the shapes.
 macro_rules! ops {
     (
-        $op_name:expr,
+        $scalar_op_name:expr,
+        $broadcast_name:ident, $try_broadcast_name:ident,
         $op_class:ident::$op_method:ident,
-        $op_assign_class:ident::$op_assign_method:ident
+        $op_assign_class:ident::$op_assign_method:ident, $try_op_assign_method:ident
     ) => {
+        impl NDArray {
+            pub fn $broadcast_name(&self, rhs: &NDArray) -> NDArray {
+                self.$try_broadcast_name(rhs).unwrap()
+            }
+
+            pub fn $try_broadcast_name(&self, rhs: &NDArray) -> Result<NDArray> {
+                Operator::try_new(stringify!($broadcast_name))?
+                    .push_input(self)
+                    .push_input(rhs)
+                    .try_invoke()
+            }
+
+            /// The assign operator, failing if `rhs` doesn't broadcast to
+            /// the shape of `self`.
+            pub fn $try_op_assign_method(&mut self, rhs: &NDArray) -> Result<()> {
+                let (shape, rhs_shape) = (self.try_shape()?, rhs.try_shape()?);
+                if !broadcasts_to(&rhs_shape, &shape) {
+                    return Err(Error::new(
+                        ErrorKind::ShapeMismatch,
+                        format!(
+                            "{}: cannot broadcast {:?} to the shape {:?} of the left hand side",
+                            stringify!($op_assign_method),
+                            rhs_shape,
+                            shape
+                        ),
+                    ));
+                }
+                Operator::try_new(stringify!($broadcast_name))?
+                    .push_input(self)
+                    .push_input(rhs)
+                    .try_invoke_with(self)
+            }
+        }
+
+        impl<'a, 'b> std::ops::$op_class<&'b NDArray> for &'a NDArray {
+            type Output = NDArray;
+
+            fn $op_method(self, rhs: &'b NDArray) -> NDArray {
+                self.$broadcast_name(rhs)
+            }
+        }
+
         impl std::ops::$op_class for NDArray {
             type Output = NDArray;
 
             fn $op_method(self, rhs: NDArray) -> NDArray {
-                Operator::new($op_name)
-                    .push_input(&self)
-                    .push_input(&rhs)
+                (&self).$op_method(&rhs)
+            }
+        }
+
+        impl<'a> std::ops::$op_class<&'a NDArray> for NDArray {
+            type Output = NDArray;
+
+            fn $op_method(self, rhs: &'a NDArray) -> NDArray {
+                (&self).$op_method(rhs)
+            }
+        }
+
+        impl<'a> std::ops::$op_class<NDArray> for &'a NDArray {
+            type Output = NDArray;
+
+            fn $op_method(self, rhs: NDArray) -> NDArray {
+                self.$op_method(&rhs)
+            }
+        }
+
+        impl<'a> std::ops::$op_class<f32> for &'a NDArray {
+            type Output = NDArray;
+
+            fn $op_method(self, scalar: f32) -> NDArray {
+                Operator::new($scalar_op_name)
+                    .push_input(self)
+                    .set_param("scalar", &scalar)
                     .invoke()
             }
         }
@@ -38,46 +126,130 @@ macro_rules! ops {
             type Output = NDArray;
 
             fn $op_method(self, scalar: f32) -> NDArray {
-                Operator::new(concat!($op_name, "_scalar"))
-                    .push_input(&self)
-                    // .set_param("scalar", &scalar)
-                    .push_param(&scalar)
-                    .invoke()
+                (&self).$op_method(scalar)
+            }
+        }
+
+        impl<'a> std::ops::$op_assign_class<&'a NDArray> for NDArray {
+            fn $op_assign_method(&mut self, rhs: &'a NDArray) {
+                self.$try_op_assign_method(rhs).unwrap()
             }
         }
 
         impl std::ops::$op_assign_class for NDArray {
             fn $op_assign_method(&mut self, rhs: NDArray) {
-                Operator::new($op_name)
-                    .push_input(self)
-                    .push_input(&rhs)
-                    .invoke_with(self);
+                self.$op_assign_method(&rhs);
             }
         }
 
         impl std::ops::$op_assign_class<f32> for NDArray {
             fn $op_assign_method(&mut self, scalar: f32) {
-                Operator::new(concat!($op_name, "_scalar"))
+                Operator::new($scalar_op_name)
                     .push_input(self)
-                    // .set_param("scalar", &rhs)
-                    .push_param(&scalar)
+                    .set_param("scalar", &scalar)
                     .invoke_with(self);
             }
         }
     };
 }
-// pub enum DType {
-//     None = -1,
-//     F32 = 0,
-//     F64 = 1,
-//     F16 = 2,
-//     U8 = 3,
-//     I32 = 4,
-//     I8 = 5,
-//     I64 = 6,
-// }
 
-#[derive(Debug)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum DType {
+    None = -1,
+    F32 = 0,
+    F64 = 1,
+    F16 = 2,
+    U8 = 3,
+    I32 = 4,
+    I8 = 5,
+    I64 = 6,
+}
+
+impl DType {
+    /// Size of one element in bytes.
+    pub fn size(&self) -> usize {
+        match self {
+            DType::None => 0,
+            DType::U8 | DType::I8 => 1,
+            DType::F16 => 2,
+            DType::F32 | DType::I32 => 4,
+            DType::F64 | DType::I64 => 8,
+        }
+    }
+}
+
+impl TryFrom<i32> for DType {
+    type Error = Error;
+
+    fn try_from(int: i32) -> Result<DType> {
+        match int {
+            -1 => Ok(DType::None),
+            0 => Ok(DType::F32),
+            1 => Ok(DType::F64),
+            2 => Ok(DType::F16),
+            3 => Ok(DType::U8),
+            4 => Ok(DType::I32),
+            5 => Ok(DType::I8),
+            6 => Ok(DType::I64),
+            _ => Err(Error::new(
+                ErrorKind::TypeMismatch,
+                format!("unsupported dtype {}", int),
+            )),
+        }
+    }
+}
+
+// Names used by MXNet operators, e.g. the `dtype` param of `Cast`.
+impl fmt::Display for DType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            DType::None => "None",
+            DType::F32 => "float32",
+            DType::F64 => "float64",
+            DType::F16 => "float16",
+            DType::U8 => "uint8",
+            DType::I32 => "int32",
+            DType::I8 => "int8",
+            DType::I64 => "int64",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Rust types that can be stored in an NDArray.
+///
+/// Sealed, since copying data in and out reinterprets the bytes as `DTYPE`.
+pub trait Element: sealed::Sealed + Copy + 'static {
+    const DTYPE: DType;
+}
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+macro_rules! element {
+    ($($ty:ty => $dtype:ident),*) => {
+        $(
+            impl sealed::Sealed for $ty {}
+
+            impl Element for $ty {
+                const DTYPE: DType = DType::$dtype;
+            }
+        )*
+    };
+}
+
+element!(
+    f32 => F32,
+    f64 => F64,
+    f16 => F16,
+    u8 => U8,
+    i32 => I32,
+    i8 => I8,
+    i64 => I64
+);
+
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub enum StorageType {
     Undefined = -1,
     Default = 0,
@@ -85,18 +257,35 @@ pub enum StorageType {
     CSR = 2,
 }
 
-impl From<i32> for StorageType {
-    fn from(int: i32) -> StorageType {
+impl TryFrom<i32> for StorageType {
+    type Error = Error;
+
+    fn try_from(int: i32) -> Result<StorageType> {
         match int {
-            -1 => StorageType::Undefined,
-            0 => StorageType::Default,
-            1 => StorageType::RowSparse,
-            2 => StorageType::CSR,
-            _ => unreachable!(),
+            -1 => Ok(StorageType::Undefined),
+            0 => Ok(StorageType::Default),
+            1 => Ok(StorageType::RowSparse),
+            2 => Ok(StorageType::CSR),
+            _ => Err(Error::new(
+                ErrorKind::TypeMismatch,
+                format!("unsupported storage type {}", int),
+            )),
         }
     }
 }
 
+impl fmt::Display for StorageType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            StorageType::Undefined => "undefined",
+            StorageType::Default => "default",
+            StorageType::RowSparse => "row_sparse",
+            StorageType::CSR => "csr",
+        };
+        write!(f, "{}", name)
+    }
+}
+
 // impl From<&str> for StorageType {
 //     fn from(s: &str) -> StorageType {
 //         match s {
@@ -109,6 +298,14 @@ impl From<i32> for StorageType {
 //     }
 // }
 
+/// How gradients are written into the buffer attached by `attach_grad`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum GradReq {
+    Null = 0,
+    Write = 1,
+    Add = 3,
+}
+
 struct NDBlob {
     handle: NDArrayHandle,
 }
@@ -139,20 +336,119 @@ pub struct NDArray {
     writable: bool,
 }
 
-ops!("_plus", Add::add, AddAssign::add_assign);
-ops!("_minus", Sub::sub, SubAssign::sub_assign);
-ops!("_mul", Mul::mul, MulAssign::mul_assign);
-ops!("_div", Div::div, DivAssign::div_assign);
-ops!("_mod", Rem::rem, RemAssign::rem_assign);
+ops!(
+    "_plus_scalar",
+    broadcast_add,
+    try_broadcast_add,
+    Add::add,
+    AddAssign::add_assign,
+    try_add_assign
+);
+ops!(
+    "_minus_scalar",
+    broadcast_sub,
+    try_broadcast_sub,
+    Sub::sub,
+    SubAssign::sub_assign,
+    try_sub_assign
+);
+ops!(
+    "_mul_scalar",
+    broadcast_mul,
+    try_broadcast_mul,
+    Mul::mul,
+    MulAssign::mul_assign,
+    try_mul_assign
+);
+ops!(
+    "_div_scalar",
+    broadcast_div,
+    try_broadcast_div,
+    Div::div,
+    DivAssign::div_assign,
+    try_div_assign
+);
+ops!(
+    "_mod_scalar",
+    broadcast_mod,
+    try_broadcast_mod,
+    Rem::rem,
+    RemAssign::rem_assign,
+    try_rem_assign
+);
+
+// Whether broadcasting `from` against `to` gives `to`, so that an assign
+// op can write the result in place. Shapes are aligned to the right.
+fn broadcasts_to(from: &[u32], to: &[u32]) -> bool {
+    from.len() <= to.len()
+        && from
+            .iter()
+            .rev()
+            .zip(to.iter().rev())
+            .all(|(from, to)| from == to || *from == 1)
+}
+
+// Implement `f32 op NDArray` for the ops where the order matters.
+macro_rules! rscalar_ops {
+    (
+        $op_name:expr,
+        $op_class:ident::$op_method:ident
+    ) => {
+        impl<'a> std::ops::$op_class<&'a NDArray> for f32 {
+            type Output = NDArray;
+
+            fn $op_method(self, rhs: &'a NDArray) -> NDArray {
+                Operator::new($op_name)
+                    .push_input(rhs)
+                    .set_param("scalar", &self)
+                    .invoke()
+            }
+        }
+
+        impl std::ops::$op_class<NDArray> for f32 {
+            type Output = NDArray;
+
+            fn $op_method(self, rhs: NDArray) -> NDArray {
+                self.$op_method(&rhs)
+            }
+        }
+    };
+}
+
+rscalar_ops!("_plus_scalar", Add::add);
+rscalar_ops!("_rminus_scalar", Sub::sub);
+rscalar_ops!("_mul_scalar", Mul::mul);
+rscalar_ops!("_rdiv_scalar", Div::div);
+rscalar_ops!("_rmod_scalar", Rem::rem);
+
+impl<'a> std::ops::Neg for &'a NDArray {
+    type Output = NDArray;
+
+    fn neg(self) -> NDArray {
+        Operator::new("negative").push_input(self).invoke()
+    }
+}
+
+impl std::ops::Neg for NDArray {
+    type Output = NDArray;
+
+    fn neg(self) -> NDArray {
+        -&self
+    }
+}
 
 impl NDArray {
     pub fn new() -> NDArray {
+        NDArray::try_new().unwrap()
+    }
+
+    pub fn try_new() -> Result<NDArray> {
         let mut handle = ptr::null_mut();
-        check_call!(MXNDArrayCreateNone(&mut handle));
-        NDArray {
+        try_call!(MXNDArrayCreateNone(&mut handle))?;
+        Ok(NDArray {
             blob: Rc::new(NDBlob::new(handle)),
             writable: true,
-        }
+        })
     }
 
     pub fn builder() -> NDArrayBuilder {
@@ -160,39 +456,133 @@ impl NDArray {
     }
 
     pub fn wait_to_read(&self) {
-        check_call!(MXNDArrayWaitToRead(self.handle()));
+        self.try_wait_to_read().unwrap()
+    }
+
+    pub fn try_wait_to_read(&self) -> Result<()> {
+        try_call!(MXNDArrayWaitToRead(self.handle()))
     }
 
     pub fn wait_to_write(&self) {
-        check_call!(MXNDArrayWaitToWrite(self.handle()));
+        self.try_wait_to_write().unwrap()
+    }
+
+    pub fn try_wait_to_write(&self) -> Result<()> {
+        try_call!(MXNDArrayWaitToWrite(self.handle()))
     }
 
     pub fn copy_to<'a>(&self, other: &'a mut NDArray) -> &'a mut NDArray {
-        Operator::new("copyto").push_input(self).invoke_with(other);
-        other
+        self.try_copy_to(other).unwrap()
+    }
+
+    pub fn try_copy_to<'a>(&self, other: &'a mut NDArray) -> Result<&'a mut NDArray> {
+        Operator::try_new("copyto")?
+            .push_input(self)
+            .try_invoke_with(other)?;
+        Ok(other)
     }
 
     pub fn wait_all(&self) {
-        check_call!(MXNDArrayWaitAll());
+        self.try_wait_all().unwrap()
+    }
+
+    pub fn try_wait_all(&self) -> Result<()> {
+        try_call!(MXNDArrayWaitAll())
     }
 
     pub fn argmax_channel(&self) -> NDArray {
-        let mut ret = NDArray::new();
-        Operator::new("argmax_channel")
+        self.try_argmax_channel().unwrap()
+    }
+
+    pub fn try_argmax_channel(&self) -> Result<NDArray> {
+        Operator::try_new("argmax_channel")?
+            .push_input(self)
+            .try_invoke()
+    }
+
+    /// Returns a copy of the array converted to `dtype`.
+    pub fn astype(&self, dtype: DType) -> NDArray {
+        self.try_astype(dtype).unwrap()
+    }
+
+    pub fn try_astype(&self, dtype: DType) -> Result<NDArray> {
+        Operator::try_new("Cast")?
             .push_input(self)
-            .invoke_with(&mut ret);
-        ret
+            .try_set_param("dtype", &dtype)?
+            .try_invoke()
+    }
+
+    pub fn cast<T: Element>(&self) -> NDArray {
+        self.astype(T::DTYPE)
+    }
+
+    pub fn try_cast<T: Element>(&self) -> Result<NDArray> {
+        self.try_astype(T::DTYPE)
     }
 
     pub fn slice(&self, begin: u32, end: u32) -> NDArray {
+        self.try_slice(begin, end).unwrap()
+    }
+
+    pub fn try_slice(&self, begin: u32, end: u32) -> Result<NDArray> {
         let mut handle = ptr::null_mut();
-        check_call!(MXNDArraySlice(self.handle(), begin, end, &mut handle));
-        NDArray::from(handle)
+        try_call!(MXNDArraySlice(self.handle(), begin, end, &mut handle))?;
+        Ok(NDArray::from(handle))
+    }
+
+    /// Attach a gradient buffer to this NDArray, so that `backward`
+    /// can compute gradient with respect to it.
+    pub fn attach_grad(&self) {
+        self.attach_grad_with(GradReq::Write, None)
+    }
+
+    pub fn try_attach_grad(&self) -> Result<()> {
+        self.try_attach_grad_with(GradReq::Write, None)
     }
 
-    pub fn attach_grad(&self) {}
+    pub fn attach_grad_with(&self, grad_req: GradReq, stype: Option<StorageType>) {
+        self.try_attach_grad_with(grad_req, stype).unwrap()
+    }
+
+    pub fn try_attach_grad_with(
+        &self,
+        grad_req: GradReq,
+        stype: Option<StorageType>,
+    ) -> Result<()> {
+        let mut grad = Operator::try_new("zeros_like")?
+            .push_input(self)
+            .try_invoke()?;
+        if let Some(stype) = stype {
+            grad = Operator::try_new("cast_storage")?
+                .push_input(&grad)
+                .try_set_param("stype", &stype)?
+                .try_invoke()?;
+        }
+        autograd::try_mark_variables(&[self], &[&grad], &[grad_req])
+    }
 
-    pub fn attach_grad_with(&self, grad_req: &str, stype: Option<&str>) {}
+    /// Compute the gradients of this NDArray w.r.t variables.
+    ///
+    /// `out_grad` defaults to ones when `None`.
+    pub fn backward(&self, out_grad: Option<&NDArray>, retain_graph: bool, train_mode: bool) {
+        self.try_backward(out_grad, retain_graph, train_mode)
+            .unwrap()
+    }
+
+    pub fn try_backward(
+        &self,
+        out_grad: Option<&NDArray>,
+        retain_graph: bool,
+        train_mode: bool,
+    ) -> Result<()> {
+        let out_grads = out_grad.map(|out_grad| [out_grad]);
+        autograd::try_backward(
+            &[self],
+            out_grads.as_ref().map(|grads| &grads[..]),
+            retain_graph,
+            train_mode,
+        )
+    }
 
     pub fn set_writable(&mut self, writable: bool) -> &mut Self {
         self.writable = writable;
@@ -218,105 +608,279 @@ impl From<NDArrayHandle> for NDArray {
 /// Properties
 impl NDArray {
     pub fn size(&self) -> u32 {
-        self.raw_shape().iter().fold(1, |acc, x| acc * *x)
+        self.try_size().unwrap()
+    }
+
+    pub fn try_size(&self) -> Result<u32> {
+        Ok(self.try_raw_shape()?.iter().fold(1, |acc, x| acc * *x))
     }
 
     pub fn shape(&self) -> Vec<u32> {
-        let ret_slice = self.raw_shape();
+        self.try_shape().unwrap()
+    }
+
+    pub fn try_shape(&self) -> Result<Vec<u32>> {
+        let ret_slice = self.try_raw_shape()?;
         let mut ret = Vec::with_capacity(ret_slice.len());
         for i in ret_slice {
             ret.push(*i);
         }
-        ret
+        Ok(ret)
     }
 
-    // Not sure if we want a enum instead of i32.
-    // Not sure if we realy want dtype.
-    pub fn dtype(&self) -> i32 {
-        self.raw_dtype()
+    pub fn dtype(&self) -> DType {
+        self.try_dtype().unwrap()
+    }
+
+    pub fn try_dtype(&self) -> Result<DType> {
+        DType::try_from(self.try_raw_dtype()?)
     }
 
     pub fn context(&self) -> Context {
+        self.try_context().unwrap()
+    }
+
+    pub fn try_context(&self) -> Result<Context> {
         let mut out_dev_type = 0;
         let mut out_dev_id = 0;
-        check_call!(MXNDArrayGetContext(
+        try_call!(MXNDArrayGetContext(
             self.handle(),
             &mut out_dev_type,
             &mut out_dev_id
-        ));
-        Context::new(DeviceType::from(out_dev_type), out_dev_id)
+        ))?;
+        Ok(Context::new(DeviceType::from(out_dev_type), out_dev_id))
     }
 
-    pub fn data(&self) -> &[f32] {
+    /// Borrow the underlying cpu buffer, panics if `T` does not match `dtype()`.
+    ///
+    /// Waits for pending writes first, but nothing stops a later write
+    /// while the slice is alive: clones share the buffer, and `+=` or
+    /// `invoke_with` on any of them changes the data under the slice, maybe
+    /// from another MXNet thread. Only use it on arrays nothing writes to
+    /// anymore, and prefer `to_vec`, which copies.
+    pub fn data<T: Element>(&self) -> &[T] {
+        self.try_data().unwrap()
+    }
+
+    pub fn try_data<T: Element>(&self) -> Result<&[T]> {
+        self.check_dtype::<T>()?;
+        if self.try_context()?.device_type() == DeviceType::GPU {
+            return Err(Error::new(
+                ErrorKind::InvalidParameter,
+                "cannot borrow data of a gpu NDArray, use to_vec instead",
+            ));
+        }
+        self.try_wait_to_read()?;
         let mut ret = ptr::null_mut();
-        check_call!(MXNDArrayGetData(self.handle(), &mut ret));
-        unsafe { mem::transmute(slice::from_raw_parts(ret, self.size() as usize)) }
+        try_call!(MXNDArrayGetData(self.handle(), &mut ret))?;
+        Ok(unsafe { slice::from_raw_parts(ret as *const T, self.try_size()? as usize) })
+    }
+
+    /// Copy the data to a new host `Vec`, waiting for pending writes.
+    pub fn to_vec<T: Element>(&self) -> Vec<T> {
+        self.try_to_vec().unwrap()
+    }
+
+    pub fn try_to_vec<T: Element>(&self) -> Result<Vec<T>> {
+        let size = self.try_size()? as usize;
+        let mut ret = Vec::with_capacity(size);
+        unsafe {
+            self.try_sync_copy_to_cpu(ret.as_mut_ptr(), size)?;
+            ret.set_len(size);
+        }
+        Ok(ret)
+    }
+
+    /// Copy the data into `dst`, whose length must equal `size()`.
+    pub fn copy_to_slice<T: Element>(&self, dst: &mut [T]) {
+        self.try_copy_to_slice(dst).unwrap()
+    }
+
+    pub fn try_copy_to_slice<T: Element>(&self, dst: &mut [T]) -> Result<()> {
+        let size = self.try_size()? as usize;
+        if dst.len() != size {
+            return Err(Error::new(
+                ErrorKind::ShapeMismatch,
+                format!(
+                    "destination has {} elements, but NDArray has {}",
+                    dst.len(),
+                    size
+                ),
+            ));
+        }
+        unsafe { self.try_sync_copy_to_cpu(dst.as_mut_ptr(), size) }
     }
 
     pub fn grad(&self) -> NDArray {
+        self.try_grad().unwrap()
+    }
+
+    pub fn try_grad(&self) -> Result<NDArray> {
         let mut handle = ptr::null_mut();
-        check_call!(MXNDArrayGetGrad(self.handle(), &mut handle));
-        NDArray::from(handle)
+        try_call!(MXNDArrayGetGrad(self.handle(), &mut handle))?;
+        Ok(NDArray::from(handle))
     }
 
     pub fn stype(&self) -> StorageType {
-        StorageType::from(self.storage_type())
+        self.try_stype().unwrap()
+    }
+
+    pub fn try_stype(&self) -> Result<StorageType> {
+        StorageType::try_from(self.try_storage_type()?)
     }
 }
 
 /// Private
 impl NDArray {
-    fn raw_shape(&self) -> &[u32] {
+    fn try_raw_shape(&self) -> Result<&[u32]> {
         let mut out_dim = 0;
         let mut out_pdata = ptr::null();
 
-        check_call!(MXNDArrayGetShape(
+        try_call!(MXNDArrayGetShape(
             self.handle(),
             &mut out_dim,
             &mut out_pdata
-        ));
-        unsafe { slice::from_raw_parts(out_pdata, out_dim as usize) }
+        ))?;
+        Ok(unsafe { slice::from_raw_parts(out_pdata, out_dim as usize) })
     }
 
-    fn raw_dtype(&self) -> i32 {
+    fn try_raw_dtype(&self) -> Result<i32> {
         let mut mx_dtype = 0;
-        check_call!(MXNDArrayGetDType(self.handle(), &mut mx_dtype));
-        mx_dtype
+        try_call!(MXNDArrayGetDType(self.handle(), &mut mx_dtype))?;
+        Ok(mx_dtype)
     }
 
-    fn storage_type(&self) -> i32 {
-        let mut storage_type = 0;
-        check_call!(MXNDArrayGetStorageType(self.handle(), &mut storage_type));
-        storage_type
+    // A view sharing memory with self, `shape` may hold fewer elements.
+    pub(crate) fn try_reshape_view(&self, shape: &[i64]) -> Result<NDArray> {
+        let mut dims = shape.to_vec();
+        let mut handle = ptr::null_mut();
+        try_call!(MXNDArrayReshape64(
+            self.handle(),
+            dims.len() as i32,
+            dims.as_mut_ptr(),
+            false,
+            &mut handle
+        ))?;
+        Ok(NDArray::from(handle))
     }
-}
 
-impl fmt::Display for NDArray {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let shape = self.shape();
-        let mut cpu_array = NDArray::builder().shape(&shape).create();
-        if self.context().device_type() != DeviceType::GPU {
-            cpu_array = self.clone();
-        } else {
-            self.wait_to_read();
-            self.copy_to(&mut cpu_array);
-        };
+    fn check_dtype<T: Element>(&self) -> Result<()> {
+        let dtype = self.try_dtype()?;
+        if dtype != T::DTYPE {
+            return Err(Error::new(
+                ErrorKind::TypeMismatch,
+                format!(
+                    "NDArray has dtype {}, but {} was requested",
+                    dtype,
+                    T::DTYPE
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    // `dst` must have room for `size` elements.
+    unsafe fn try_sync_copy_to_cpu<T: Element>(&self, dst: *mut T, size: usize) -> Result<()> {
+        self.check_dtype::<T>()?;
+        // MXNDArraySyncCopyToCPU waits for pending writes and copies from any device.
+        try_call!(MXNDArraySyncCopyToCPU(
+            self.handle(),
+            dst as *mut c_void,
+            size
+        ))
+    }
 
+    fn fmt_data<T: Element + fmt::Display>(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let data = self.to_vec::<T>();
         write!(
             f,
-            "{}\ndtype={}",
+            "{}",
             ArrayView::from_shape(
-                Dim(cpu_array
+                Dim(self
                     .shape()
                     .into_iter()
                     .map(|s| s as usize)
                     .collect::<Vec<usize>>()),
-                cpu_array.data()
+                &data[..]
             )
-            .unwrap(),
-            cpu_array.dtype()
+            .unwrap()
         )
     }
+
+    fn try_storage_type(&self) -> Result<i32> {
+        let mut storage_type = 0;
+        try_call!(MXNDArrayGetStorageType(self.handle(), &mut storage_type))?;
+        Ok(storage_type)
+    }
+}
+
+/// Conversion to `ndarray::Array`
+impl NDArray {
+    /// Copy the data into an owned `Array` of dimension `D`.
+    pub fn to_ndarray<T: Element, D: Dimension>(&self) -> Array<T, D> {
+        self.try_to_ndarray().unwrap()
+    }
+
+    pub fn try_to_ndarray<T: Element, D: Dimension>(&self) -> Result<Array<T, D>> {
+        let data = self.try_to_vec()?;
+        Array::from_shape_vec(self.try_ndarray_shape()?, data)
+            .and_then(|array| array.into_dimensionality())
+            .map_err(|e| Error::new(ErrorKind::ShapeMismatch, e.to_string()))
+    }
+
+    /// Borrow the cpu buffer as an `ArrayView` of dimension `D`, see `data`.
+    pub fn as_array_view<T: Element, D: Dimension>(&self) -> ArrayView<'_, T, D> {
+        self.try_as_array_view().unwrap()
+    }
+
+    pub fn try_as_array_view<T: Element, D: Dimension>(&self) -> Result<ArrayView<'_, T, D>> {
+        ArrayView::from_shape(self.try_ndarray_shape()?, self.try_data()?)
+            .and_then(|view| view.into_dimensionality())
+            .map_err(|e| Error::new(ErrorKind::ShapeMismatch, e.to_string()))
+    }
+
+    fn try_ndarray_shape(&self) -> Result<IxDyn> {
+        Ok(Dim(self
+            .try_raw_shape()?
+            .iter()
+            .map(|s| *s as usize)
+            .collect::<Vec<usize>>()))
+    }
+}
+
+impl<'a, T, S, D> From<&'a ArrayBase<S, D>> for NDArray
+where
+    T: Element,
+    S: Data<Elem = T>,
+    D: Dimension,
+{
+    fn from(array: &'a ArrayBase<S, D>) -> NDArray {
+        let shape: Vec<u32> = array.shape().iter().map(|s| *s as u32).collect();
+        let mut builder = NDArray::builder();
+        // Non-contiguous layouts are copied in logical order first.
+        match array.as_slice() {
+            Some(data) => builder.data(data),
+            None => builder.data(&array.iter().cloned().collect::<Vec<T>>()),
+        };
+        builder.shape(&shape).create()
+    }
+}
+
+impl fmt::Display for NDArray {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let dtype = self.dtype();
+        match dtype {
+            DType::F32 => self.fmt_data::<f32>(f)?,
+            DType::F64 => self.fmt_data::<f64>(f)?,
+            DType::F16 => self.fmt_data::<f16>(f)?,
+            DType::U8 => self.fmt_data::<u8>(f)?,
+            DType::I32 => self.fmt_data::<i32>(f)?,
+            DType::I8 => self.fmt_data::<i8>(f)?,
+            DType::I64 => self.fmt_data::<i64>(f)?,
+            DType::None => write!(f, "[]")?,
+        }
+        write!(f, "\ndtype={}", dtype)
+    }
 }
 
 // impl std::ops::Add for NDArray {
@@ -364,9 +928,12 @@ impl fmt::Display for NDArray {
 // }
 
 pub struct NDArrayBuilder {
-    data: Vec<f32>,
+    // Raw bytes of the data, whose element type is `data_dtype`.
+    data: Vec<u8>,
+    data_dtype: DType,
     shape: Vec<u32>,
     context: Context,
+    dtype: DType,
     delay_alloc: bool,
     writable: bool,
 }
@@ -375,20 +942,33 @@ impl NDArrayBuilder {
     pub fn new() -> NDArrayBuilder {
         NDArrayBuilder {
             data: Vec::new(),
+            data_dtype: DType::F32,
             shape: Vec::new(),
             context: Default::default(),
+            dtype: DType::F32,
             delay_alloc: true,
             writable: true,
         }
     }
 
-    pub fn data(&mut self, data: &[f32]) -> &mut Self {
-        self.data = data.to_vec();
+    /// Also sets `dtype` to match `T`, call `dtype` afterwards to cast.
+    pub fn data<T: Element>(&mut self, data: &[T]) -> &mut Self {
+        self.data = unsafe {
+            slice::from_raw_parts(data.as_ptr() as *const u8, data.len() * mem::size_of::<T>())
+        }
+        .to_vec();
+        self.data_dtype = T::DTYPE;
+        self.dtype = T::DTYPE;
         // Set data as 1-D array.
         self.shape = vec![data.len() as u32];
         self
     }
 
+    pub fn dtype(&mut self, dtype: DType) -> &mut Self {
+        self.dtype = dtype;
+        self
+    }
+
     pub fn shape(&mut self, shape: &[u32]) -> &mut Self {
         self.shape = shape.to_vec();
         self
@@ -410,36 +990,52 @@ impl NDArrayBuilder {
     }
 
     pub fn create(&self) -> NDArray {
+        self.try_create().unwrap()
+    }
+
+    pub fn try_create(&self) -> Result<NDArray> {
         let mut handle = ptr::null_mut();
+        let dtype = if self.data.is_empty() {
+            self.dtype
+        } else {
+            self.data_dtype
+        };
 
-        check_call!(MXNDArrayCreate(
+        try_call!(MXNDArrayCreateEx(
             self.shape.as_ptr(),
             self.shape.len() as u32,
             self.context.device_type() as i32,
             self.context.device_id() as i32,
             // Only when no data do we delay alloc.
             (self.data.is_empty() && self.delay_alloc) as i32,
+            dtype as i32,
             &mut handle
-        ));
+        ))?;
+
+        let mut array = NDArray {
+            blob: Rc::new(NDBlob::new(handle)),
+            writable: self.writable,
+        };
 
         if !self.data.is_empty() {
-            check_call!(MXNDArraySyncCopyFromCPU(
+            try_call!(MXNDArraySyncCopyFromCPU(
                 handle,
                 self.data.as_ptr() as *const c_void,
-                self.data.len()
-            ));
-        }
+                self.data.len() / dtype.size()
+            ))?;
 
-        NDArray {
-            blob: Rc::new(NDBlob::new(handle)),
-            writable: self.writable,
+            if self.dtype != dtype {
+                array = array.try_astype(self.dtype)?;
+                array.writable = self.writable;
+            }
         }
+
+        Ok(array)
     }
 }
 
-pub fn ones() {}
-
-pub fn zeros_like() {}
+// Typed wrappers for every registered operator, see `build/op_gen.rs`.
+include!(concat!(env!("OUT_DIR"), "/ndarray_op.rs"));
 
 #[cfg(test)]
 mod tests {
@@ -466,6 +1062,150 @@ mod tests {
         println!("{:?}", a1.stype());
     }
 
+    #[test]
+    fn reversed_scalar_ops() {
+        let a = NDArray::builder().data(&[1.0f32, 2.0, 4.0]).create();
+        assert_eq!((1.0 - a.clone()).to_vec::<f32>(), vec![0.0, -1.0, -3.0]);
+        assert_eq!((4.0 / a.clone()).to_vec::<f32>(), vec![4.0, 2.0, 1.0]);
+        assert_eq!((3.0 % a.clone()).to_vec::<f32>(), vec![0.0, 1.0, 3.0]);
+        assert_eq!((2.0 * a.clone()).to_vec::<f32>(), vec![2.0, 4.0, 8.0]);
+        assert_eq!((-a).to_vec::<f32>(), vec![-1.0, -2.0, -4.0]);
+    }
+
+    #[test]
+    fn borrowed_ops() {
+        let a = NDArray::builder().data(&[1.0f32, 2.0]).create();
+        let b = NDArray::builder().data(&[3.0f32, 4.0]).create();
+        let c = NDArray::builder().data(&[0.5f32]).create();
+
+        assert_eq!((&a * &b + &c).to_vec::<f32>(), vec![3.5, 8.5]);
+        assert_eq!((&a - 1.0).to_vec::<f32>(), vec![0.0, 1.0]);
+        assert_eq!((2.0 / &a).to_vec::<f32>(), vec![2.0, 1.0]);
+        assert_eq!((-&a).to_vec::<f32>(), vec![-1.0, -2.0]);
+
+        let col = NDArray::builder()
+            .data(&[10.0f32, 20.0])
+            .shape(&[2, 1])
+            .create();
+        let row = a.reshape(&[1, 2]);
+        assert_eq!(
+            col.broadcast_add(&row).to_vec::<f32>(),
+            vec![11.0, 12.0, 21.0, 22.0]
+        );
+        assert_eq!((&col * &row).shape(), vec![2, 2]);
+        let other = NDArray::builder()
+            .data(&[0.0f32; 6])
+            .shape(&[3, 2])
+            .create();
+        assert!(col.try_broadcast_add(&other).is_err());
+
+        let mut d = a.clone();
+        d += &b;
+        d -= &c;
+        assert_eq!(d.to_vec::<f32>(), vec![3.5, 5.5]);
+
+        let mut e = col.broadcast_add(&row);
+        e *= &row;
+        assert_eq!(e.to_vec::<f32>(), vec![11.0, 24.0, 21.0, 44.0]);
+    }
+
+    #[test]
+    fn assign_op_cannot_grow_lhs() {
+        let mut col = NDArray::builder()
+            .data(&[10.0f32, 20.0])
+            .shape(&[2, 1])
+            .create();
+        let row = NDArray::builder()
+            .data(&[1.0f32, 2.0])
+            .shape(&[1, 2])
+            .create();
+        let err = col.try_add_assign(&row).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::ShapeMismatch);
+        assert!(err.message().contains("cannot broadcast"));
+        assert_eq!(col.to_vec::<f32>(), vec![10.0, 20.0]);
+    }
+
+    #[test]
+    fn ndarray_dtype() {
+        let a = NDArray::builder().data(&[1i32, 2, 3]).create();
+        assert_eq!(a.dtype(), DType::I32);
+        a.wait_to_read();
+        assert_eq!(a.data::<i32>(), &[1, 2, 3]);
+        assert!(a.try_data::<f32>().is_err());
+
+        let b = a.cast::<f64>();
+        b.wait_to_read();
+        assert_eq!(b.dtype(), DType::F64);
+        assert_eq!(b.data::<f64>(), &[1.0, 2.0, 3.0]);
+
+        let c = NDArray::builder()
+            .data(&[1.5f32, 2.5])
+            .dtype(DType::F16)
+            .create();
+        assert_eq!(c.dtype(), DType::F16);
+        println!("{}", c);
+
+        assert_eq!(DType::try_from(6).unwrap(), DType::I64);
+        let err = DType::try_from(12).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::TypeMismatch);
+    }
+
+    #[test]
+    fn generated_multi_output_ops() {
+        let a = NDArray::builder()
+            .data(&[3.0f32, 1.0, 2.0, 4.0])
+            .shape(&[2, 2])
+            .create();
+
+        let parts = op::SliceChannel::new(&a).num_outputs(2).invoke();
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[1].to_vec::<f32>(), vec![1.0, 4.0]);
+
+        let out = op::Topk::new(&a).k(1).invoke_all();
+        assert_eq!(out.value.to_vec::<f32>(), vec![3.0, 4.0]);
+        assert_eq!(out.indices.to_vec::<f32>(), vec![0.0, 1.0]);
+    }
+
+    #[test]
+    fn invalid_params_are_errors() {
+        let a = NDArray::builder().data(&[1.0f32, 2.0]).create();
+        let err = a.try_astype(DType::None).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::InvalidParameter);
+        let err = a.try_sum(3).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::InvalidParameter);
+    }
+
+    #[test]
+    fn ndarray_conversion() {
+        use ndarray::{arr2, Ix2};
+
+        let a = arr2(&[[1.0f32, 2.0, 3.0], [4.0, 5.0, 6.0]]);
+        let nd = NDArray::from(&a);
+        assert_eq!(nd.shape(), vec![2, 3]);
+        assert_eq!(nd.to_ndarray::<f32, Ix2>(), a);
+
+        // Transposed view is not in standard layout.
+        let t = a.t();
+        let nd = NDArray::from(&t);
+        assert_eq!(nd.shape(), vec![3, 2]);
+        assert_eq!(nd.as_array_view::<f32, Ix2>(), t);
+        assert!(nd.try_to_ndarray::<f32, ndarray::Ix1>().is_err());
+    }
+
+    #[test]
+    fn ndarray_to_vec() {
+        let mut a = NDArray::builder().data(&[1.0f32, 2.0, 3.0]).create();
+        a += 1.0;
+        assert_eq!(a.to_vec::<f32>(), vec![2.0, 3.0, 4.0]);
+
+        let mut dst = [0.0f32; 3];
+        a.copy_to_slice(&mut dst);
+        assert_eq!(dst, [2.0, 3.0, 4.0]);
+
+        assert!(a.try_copy_to_slice(&mut [0.0f32; 2]).is_err());
+        assert!(a.try_to_vec::<i32>().is_err());
+    }
+
     #[test]
     fn multi_thread() {
         use std::thread;
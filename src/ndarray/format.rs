@@ -0,0 +1,676 @@
+//! Pure Rust reader and writer for the NDArray list format produced by
+//! `MXNDArraySave` (`.params` / `.nd` files), no libmxnet needed.
+//!
+//! Layout, all little endian:
+//!
+//! ```text
+//! u64 list magic (0x112), u64 reserved
+//! u64 count, `count` arrays
+//! u64 count, `count` names (u64 length + utf-8 bytes)
+//! ```
+//!
+//! Each array is written as
+//!
+//! ```text
+//! u32 magic, i32 stype, [storage shape if sparse], shape,
+//! i32 dev_type, i32 dev_id, i32 dtype,
+//! [i32 aux dtype, aux shape] per aux, data bytes, aux data bytes
+//! ```
+
+use super::{DType, Element, StorageType};
+use crate::context::{Context, DeviceType};
+use crate::error::{Error, ErrorKind, Result};
+use half::f16;
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::mem;
+use std::path::Path;
+use std::ptr;
+use std::slice;
+
+const LIST_MAGIC: u64 = 0x112;
+// No storage type. Files older than V1 have no magic and u32 dims.
+const V1_MAGIC: u32 = 0xf993_fac8;
+// Shape is u32 ndim + i64 dims, with storage type.
+const V2_MAGIC: u32 = 0xf993_fac9;
+// Same as V2, but ndim is i32 and -1 means unknown (numpy shape semantics).
+const V3_MAGIC: u32 = 0xf993_faca;
+
+/// One stored NDArray, data kept as raw little endian bytes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NDArrayData {
+    pub stype: StorageType,
+    pub shape: Vec<i64>,
+    /// Shape of `data` for sparse arrays, equals `shape` for dense ones.
+    pub storage_shape: Vec<i64>,
+    pub context: Context,
+    pub dtype: DType,
+    pub data: Vec<u8>,
+    /// Index arrays of sparse storage, empty for dense ones.
+    pub aux: Vec<AuxData>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuxData {
+    pub dtype: DType,
+    pub shape: Vec<i64>,
+    pub data: Vec<u8>,
+}
+
+/// Content of a `.params` file: arrays and, optionally, their names.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct NDArrayList {
+    pub arrays: Vec<NDArrayData>,
+    /// Either empty or as long as `arrays`.
+    pub names: Vec<String>,
+}
+
+impl NDArrayData {
+    /// A dense cpu array holding a copy of `data`, which must have as many
+    /// elements as `shape`.
+    pub fn from_slice<T: Element>(shape: &[i64], data: &[T]) -> Result<NDArrayData> {
+        if num_elements(shape) != data.len() {
+            return Err(Error::new(
+                ErrorKind::InvalidParameter,
+                format!(
+                    "shape {:?} has {} elements, but got {} values",
+                    shape,
+                    num_elements(shape),
+                    data.len()
+                ),
+            ));
+        }
+        Ok(NDArrayData {
+            stype: StorageType::Default,
+            shape: shape.to_vec(),
+            storage_shape: shape.to_vec(),
+            context: Context::default(),
+            dtype: T::DTYPE,
+            data: as_bytes(data).to_vec(),
+            aux: Vec::new(),
+        })
+    }
+
+    pub fn size(&self) -> usize {
+        num_elements(&self.shape)
+    }
+
+    /// Decode `data` into values of type `T`, which must match `dtype`.
+    pub fn to_vec<T: Element>(&self) -> Result<Vec<T>> {
+        if self.dtype != T::DTYPE {
+            return Err(Error::new(
+                ErrorKind::TypeMismatch,
+                format!(
+                    "data has dtype {}, but {} was requested",
+                    self.dtype,
+                    T::DTYPE
+                ),
+            ));
+        }
+        let len = self.data.len() / mem::size_of::<T>();
+        let mut ret = Vec::<T>::with_capacity(len);
+        unsafe {
+            ptr::copy_nonoverlapping(
+                self.data.as_ptr(),
+                ret.as_mut_ptr() as *mut u8,
+                len * mem::size_of::<T>(),
+            );
+            ret.set_len(len);
+        }
+        Ok(ret)
+    }
+
+    /// Convert the values to `dtype`, going through `i64` between integer
+    /// types, so large `i64` values are kept, and through `f64` otherwise.
+    pub fn astype(&self, dtype: DType) -> Result<NDArrayData> {
+        if dtype == DType::None || self.dtype == DType::None {
+            return Err(Error::new(
+                ErrorKind::TypeMismatch,
+                format!("cannot cast from {} to {}", self.dtype, dtype),
+            ));
+        }
+        let data = if is_integer(self.dtype) && is_integer(dtype) {
+            let values = self.to_i64()?;
+            match dtype {
+                DType::U8 => cast_bytes(&values, |v| v as u8),
+                DType::I32 => cast_bytes(&values, |v| v as i32),
+                DType::I8 => cast_bytes(&values, |v| v as i8),
+                DType::I64 => cast_bytes(&values, |v| v),
+                _ => unreachable!(),
+            }
+        } else {
+            let values = self.to_f64()?;
+            match dtype {
+                DType::F32 => cast_bytes(&values, |v| v as f32),
+                DType::F64 => cast_bytes(&values, |v| v),
+                DType::F16 => cast_bytes(&values, f16::from_f64),
+                DType::U8 => cast_bytes(&values, |v| v as u8),
+                DType::I32 => cast_bytes(&values, |v| v as i32),
+                DType::I8 => cast_bytes(&values, |v| v as i8),
+                DType::I64 => cast_bytes(&values, |v| v as i64),
+                DType::None => unreachable!(),
+            }
+        };
+        Ok(NDArrayData {
+            dtype,
+            data,
+            ..self.clone()
+        })
+    }
+
+    fn to_i64(&self) -> Result<Vec<i64>> {
+        Ok(match self.dtype {
+            DType::U8 => self.to_vec::<u8>()?.into_iter().map(i64::from).collect(),
+            DType::I32 => self.to_vec::<i32>()?.into_iter().map(i64::from).collect(),
+            DType::I8 => self.to_vec::<i8>()?.into_iter().map(i64::from).collect(),
+            DType::I64 => self.to_vec::<i64>()?,
+            _ => unreachable!(),
+        })
+    }
+
+    fn to_f64(&self) -> Result<Vec<f64>> {
+        Ok(match self.dtype {
+            DType::F32 => self.to_vec::<f32>()?.into_iter().map(f64::from).collect(),
+            DType::F64 => self.to_vec::<f64>()?,
+            DType::F16 => self.to_vec::<f16>()?.into_iter().map(f64::from).collect(),
+            DType::U8 => self.to_vec::<u8>()?.into_iter().map(f64::from).collect(),
+            DType::I32 => self.to_vec::<i32>()?.into_iter().map(f64::from).collect(),
+            DType::I8 => self.to_vec::<i8>()?.into_iter().map(f64::from).collect(),
+            DType::I64 => self
+                .to_vec::<i64>()?
+                .into_iter()
+                .map(|v| v as f64)
+                .collect(),
+            DType::None => Vec::new(),
+        })
+    }
+
+    pub fn read<R: Read>(reader: &mut R) -> Result<NDArrayData> {
+        let magic = read_u32(reader)?;
+        let (stype, np_shape, legacy_shape) = match magic {
+            V2_MAGIC | V3_MAGIC => (read_stype(reader)?, magic == V3_MAGIC, false),
+            V1_MAGIC => (StorageType::Default, false, false),
+            // Files older than V1 start with ndim directly.
+            _ => (StorageType::Default, false, true),
+        };
+
+        let num_aux = num_aux(stype);
+        let storage_shape = if num_aux > 0 {
+            read_shape(reader, np_shape)?
+        } else {
+            None
+        };
+        let shape = if legacy_shape {
+            let dims = (0..magic)
+                .map(|_| Ok(i64::from(read_u32(reader)?)))
+                .collect::<Result<Vec<_>>>()?;
+            Some(dims).filter(|dims| !dims.is_empty())
+        } else {
+            read_shape(reader, np_shape)?
+        };
+
+        let shape = match shape {
+            Some(shape) => shape,
+            None => {
+                return Ok(NDArrayData {
+                    stype,
+                    shape: Vec::new(),
+                    storage_shape: Vec::new(),
+                    context: Context::default(),
+                    dtype: DType::None,
+                    data: Vec::new(),
+                    aux: Vec::new(),
+                });
+            }
+        };
+
+        let dev_type = read_device_type(reader)?;
+        let dev_id = read_i32(reader)?;
+        let dtype = read_dtype(reader)?;
+
+        let mut aux_meta = Vec::with_capacity(num_aux);
+        for _ in 0..num_aux {
+            let aux_dtype = read_dtype(reader)?;
+            let aux_shape = read_shape(reader, np_shape)?.unwrap_or_default();
+            aux_meta.push((aux_dtype, aux_shape));
+        }
+
+        let storage_shape = storage_shape.unwrap_or_else(|| shape.clone());
+        let data = read_bytes(reader, byte_len(&storage_shape, dtype)?)?;
+
+        let mut aux = Vec::with_capacity(num_aux);
+        for (aux_dtype, aux_shape) in aux_meta {
+            let aux_data = read_bytes(reader, byte_len(&aux_shape, aux_dtype)?)?;
+            aux.push(AuxData {
+                dtype: aux_dtype,
+                shape: aux_shape,
+                data: aux_data,
+            });
+        }
+
+        Ok(NDArrayData {
+            stype,
+            shape,
+            storage_shape,
+            context: Context::new(dev_type, dev_id),
+            dtype,
+            data,
+            aux,
+        })
+    }
+
+    /// Write in the V2 layout, which every MXNet 1.x can load. V2 reads
+    /// ndim 0 as a none array, so 0-d arrays are written with shape `[1]`.
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<()> {
+        writer.write_all(&V2_MAGIC.to_le_bytes())?;
+        writer.write_all(&(self.stype as i32).to_le_bytes())?;
+        if num_aux(self.stype) > 0 {
+            write_shape(writer, &self.storage_shape)?;
+        }
+        if self.dtype == DType::None {
+            return write_shape(writer, &[]);
+        }
+        if self.shape.is_empty() {
+            write_shape(writer, &[1])?;
+        } else {
+            write_shape(writer, &self.shape)?;
+        }
+
+        writer.write_all(&(self.context.device_type() as i32).to_le_bytes())?;
+        writer.write_all(&self.context.device_id().to_le_bytes())?;
+        writer.write_all(&(self.dtype as i32).to_le_bytes())?;
+        for aux in &self.aux {
+            writer.write_all(&(aux.dtype as i32).to_le_bytes())?;
+            write_shape(writer, &aux.shape)?;
+        }
+        writer.write_all(&self.data)?;
+        for aux in &self.aux {
+            writer.write_all(&aux.data)?;
+        }
+        Ok(())
+    }
+}
+
+impl NDArrayList {
+    pub fn load(path: impl AsRef<Path>) -> Result<NDArrayList> {
+        NDArrayList::read(&mut BufReader::new(File::open(path)?))
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    pub fn read<R: Read>(reader: &mut R) -> Result<NDArrayList> {
+        let magic = read_u64(reader)?;
+        if magic != LIST_MAGIC {
+            return Err(Error::new(
+                ErrorKind::InvalidFormat,
+                format!("invalid NDArray list magic {:#x}", magic),
+            ));
+        }
+        // Reserved.
+        read_u64(reader)?;
+
+        let num_arrays = read_u64(reader)? as usize;
+        let mut arrays = Vec::new();
+        for _ in 0..num_arrays {
+            arrays.push(NDArrayData::read(reader)?);
+        }
+
+        let num_names = read_u64(reader)? as usize;
+        if num_names != 0 && num_names != num_arrays {
+            return Err(Error::new(
+                ErrorKind::InvalidFormat,
+                format!("{} names for {} arrays", num_names, num_arrays),
+            ));
+        }
+        let mut names = Vec::new();
+        for _ in 0..num_names {
+            let len = read_u64(reader)? as usize;
+            let name = String::from_utf8(read_bytes(reader, len)?)
+                .map_err(|e| Error::new(ErrorKind::InvalidFormat, e.to_string()))?;
+            names.push(name);
+        }
+
+        Ok(NDArrayList { arrays, names })
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<()> {
+        if !self.names.is_empty() && self.names.len() != self.arrays.len() {
+            return Err(Error::new(
+                ErrorKind::InvalidFormat,
+                format!(
+                    "{} names for {} arrays",
+                    self.names.len(),
+                    self.arrays.len()
+                ),
+            ));
+        }
+
+        writer.write_all(&LIST_MAGIC.to_le_bytes())?;
+        writer.write_all(&0u64.to_le_bytes())?;
+        writer.write_all(&(self.arrays.len() as u64).to_le_bytes())?;
+        for array in &self.arrays {
+            array.write(writer)?;
+        }
+        writer.write_all(&(self.names.len() as u64).to_le_bytes())?;
+        for name in &self.names {
+            writer.write_all(&(name.len() as u64).to_le_bytes())?;
+            writer.write_all(name.as_bytes())?;
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&NDArrayData> {
+        self.names
+            .iter()
+            .position(|n| n == name)
+            .map(|i| &self.arrays[i])
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut NDArrayData> {
+        match self.names.iter().position(|n| n == name) {
+            Some(i) => Some(&mut self.arrays[i]),
+            None => None,
+        }
+    }
+
+    /// Returns false if there is no array called `from`.
+    pub fn rename(&mut self, from: &str, to: &str) -> bool {
+        match self.names.iter_mut().find(|n| *n == from) {
+            Some(name) => {
+                *name = to.to_owned();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Keep only the named arrays for which `f` returns true,
+    /// e.g. to strip optimizer states.
+    pub fn retain<F: FnMut(&str, &NDArrayData) -> bool>(&mut self, mut f: F) {
+        let arrays = self.arrays.drain(..).collect::<Vec<_>>();
+        let names = self.names.drain(..).collect::<Vec<_>>();
+        for (name, array) in names.into_iter().zip(arrays) {
+            if f(&name, &array) {
+                self.names.push(name);
+                self.arrays.push(array);
+            }
+        }
+    }
+}
+
+fn num_aux(stype: StorageType) -> usize {
+    match stype {
+        StorageType::RowSparse => 1,
+        StorageType::CSR => 2,
+        _ => 0,
+    }
+}
+
+fn num_elements(shape: &[i64]) -> usize {
+    shape.iter().fold(1, |acc, x| acc * (*x).max(0) as usize)
+}
+
+fn as_bytes<T: Element>(data: &[T]) -> &[u8] {
+    unsafe { slice::from_raw_parts(data.as_ptr() as *const u8, mem::size_of_val(data)) }
+}
+
+fn is_integer(dtype: DType) -> bool {
+    matches!(dtype, DType::U8 | DType::I32 | DType::I8 | DType::I64)
+}
+
+fn cast_bytes<S: Copy, T: Element>(values: &[S], f: impl Fn(S) -> T) -> Vec<u8> {
+    as_bytes(&values.iter().map(|v| f(*v)).collect::<Vec<T>>()).to_vec()
+}
+
+// Lengths and shapes come from the file, so nothing is allocated upfront
+// and a truncated file is an error rather than a huge allocation.
+fn byte_len(shape: &[i64], dtype: DType) -> Result<usize> {
+    shape
+        .iter()
+        .try_fold(dtype.size(), |acc, dim| {
+            acc.checked_mul((*dim).max(0) as usize)
+        })
+        .ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidFormat,
+                format!("shape {:?} is too large", shape),
+            )
+        })
+}
+
+fn read_bytes<R: Read>(reader: &mut R, len: usize) -> Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    reader.by_ref().take(len as u64).read_to_end(&mut buf)?;
+    if buf.len() != len {
+        return Err(Error::new(
+            ErrorKind::InvalidFormat,
+            format!(
+                "expected {} bytes, but the data ends after {}",
+                len,
+                buf.len()
+            ),
+        ));
+    }
+    Ok(buf)
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> Result<u32> {
+    let mut buf = [0; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_i32<R: Read>(reader: &mut R) -> Result<i32> {
+    let mut buf = [0; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(i32::from_le_bytes(buf))
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> Result<u64> {
+    let mut buf = [0; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+fn read_i64<R: Read>(reader: &mut R) -> Result<i64> {
+    let mut buf = [0; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(i64::from_le_bytes(buf))
+}
+
+fn read_stype<R: Read>(reader: &mut R) -> Result<StorageType> {
+    let stype = read_i32(reader)?;
+    StorageType::try_from(stype).map_err(|_| {
+        Error::new(
+            ErrorKind::InvalidFormat,
+            format!("unknown storage type {}", stype),
+        )
+    })
+}
+
+fn read_device_type<R: Read>(reader: &mut R) -> Result<DeviceType> {
+    match read_i32(reader)? {
+        dev_type @ 1..=3 | dev_type @ 5 => Ok(DeviceType::from(dev_type)),
+        dev_type => Err(Error::new(
+            ErrorKind::InvalidFormat,
+            format!("unknown device type {}", dev_type),
+        )),
+    }
+}
+
+fn read_dtype<R: Read>(reader: &mut R) -> Result<DType> {
+    match read_i32(reader)? {
+        // -1 is only used for arrays without data, which have no dtype field.
+        dtype @ 0..=6 => DType::try_from(dtype),
+        dtype => Err(Error::new(
+            ErrorKind::InvalidFormat,
+            format!("unknown dtype {}", dtype),
+        )),
+    }
+}
+
+// Returns None for the shape of a none array: ndim 0 before V3, -1 in V3
+// where ndim 0 is a scalar.
+fn read_shape<R: Read>(reader: &mut R, np_shape: bool) -> Result<Option<Vec<i64>>> {
+    let ndim = if np_shape {
+        match read_i32(reader)? {
+            -1 => return Ok(None),
+            ndim => ndim as usize,
+        }
+    } else {
+        match read_u32(reader)? {
+            0 => return Ok(None),
+            ndim => ndim as usize,
+        }
+    };
+    (0..ndim)
+        .map(|_| read_i64(reader))
+        .collect::<Result<_>>()
+        .map(Some)
+}
+
+fn write_shape<W: Write>(writer: &mut W, shape: &[i64]) -> Result<()> {
+    writer.write_all(&(shape.len() as u32).to_le_bytes())?;
+    for dim in shape {
+        writer.write_all(&dim.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ndarray::{self, NDArray};
+
+    #[test]
+    fn write_and_read() {
+        let list = NDArrayList {
+            arrays: vec![
+                NDArrayData::from_slice(&[2, 2], &[1.0f32, 2.0, 3.0, 4.0]).unwrap(),
+                NDArrayData::from_slice(&[3], &[1i64, 2, 3]).unwrap(),
+            ],
+            names: vec!["arg:weight".to_owned(), "aux:mean".to_owned()],
+        };
+
+        let mut buf = Vec::new();
+        list.write(&mut buf).unwrap();
+        let mut read = NDArrayList::read(&mut &buf[..]).unwrap();
+        assert_eq!(read, list);
+
+        assert!(read.rename("aux:mean", "aux:moving_mean"));
+        read.retain(|name, _| name.starts_with("aux:"));
+        assert_eq!(read.names, vec!["aux:moving_mean".to_owned()]);
+
+        let casted = read.arrays[0].astype(DType::F16).unwrap();
+        assert_eq!(
+            casted.to_vec::<f16>().unwrap(),
+            vec![f16::from_f32(1.0), f16::from_f32(2.0), f16::from_f32(3.0)]
+        );
+    }
+
+    #[test]
+    fn from_slice_and_astype() {
+        let err = NDArrayData::from_slice(&[2, 2], &[1.0f32]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidParameter);
+
+        let big = (1i64 << 53) + 1;
+        let a = NDArrayData::from_slice(&[2], &[big, -3]).unwrap();
+        let narrowed = a.astype(DType::I32).unwrap().astype(DType::I64).unwrap();
+        assert_eq!(narrowed.to_vec::<i64>().unwrap(), vec![1, -3]);
+        let same = a.astype(DType::I64).unwrap();
+        assert_eq!(same.to_vec::<i64>().unwrap(), vec![big, -3]);
+        let bytes = a.astype(DType::U8).unwrap();
+        assert_eq!(bytes.to_vec::<u8>().unwrap(), vec![1, 253]);
+    }
+
+    #[test]
+    fn scalar_round_trip() {
+        let list = NDArrayList {
+            arrays: vec![NDArrayData::from_slice(&[], &[3.5f32]).unwrap()],
+            names: Vec::new(),
+        };
+
+        let mut buf = Vec::new();
+        list.write(&mut buf).unwrap();
+        let read = NDArrayList::read(&mut &buf[..]).unwrap();
+        assert_eq!(read.arrays[0].shape, vec![1]);
+        assert_eq!(read.arrays[0].to_vec::<f32>().unwrap(), vec![3.5]);
+    }
+
+    #[test]
+    fn invalid_device_type() {
+        let list = NDArrayList {
+            arrays: vec![NDArrayData::from_slice(&[1], &[1.0f32]).unwrap()],
+            names: Vec::new(),
+        };
+        let mut buf = Vec::new();
+        list.write(&mut buf).unwrap();
+        // After the list header, magic, stype and the shape.
+        let offset = 24 + 8 + 12;
+        buf[offset..offset + 4].copy_from_slice(&7i32.to_le_bytes());
+        let err = NDArrayList::read(&mut &buf[..]).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::InvalidFormat);
+    }
+
+    #[test]
+    fn truncated() {
+        let list = NDArrayList {
+            arrays: vec![NDArrayData::from_slice(&[4], &[1.0f32, 2.0, 3.0, 4.0]).unwrap()],
+            names: vec!["a".to_owned()],
+        };
+        let mut buf = Vec::new();
+        list.write(&mut buf).unwrap();
+
+        // A name claiming to be far longer than the file.
+        let mut long_name = buf.clone();
+        let offset = long_name.len() - 9;
+        long_name[offset..offset + 8].copy_from_slice(&u64::MAX.to_le_bytes());
+        let err = NDArrayList::read(&mut &long_name[..]).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::InvalidFormat);
+
+        // Data cut short.
+        let err = NDArrayList::read(&mut &buf[..60]).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::InvalidFormat);
+    }
+
+    #[test]
+    fn round_trip_with_mxnet() {
+        let a = NDArray::builder()
+            .data(&[1.0f32, 2.0, 3.0, 4.0, 5.0, 6.0])
+            .shape(&[2, 3])
+            .create();
+        let path = std::env::temp_dir().join(format!(
+            "mxnet_rs_format_round_trip_{}.params",
+            std::process::id()
+        ));
+        ndarray::save(&path, &[("a", &a)]);
+
+        let mut list = NDArrayList::load(&path).unwrap();
+        assert_eq!(list.names, vec!["a".to_owned()]);
+        assert_eq!(list.arrays[0].shape, vec![2, 3]);
+        assert_eq!(
+            list.arrays[0].to_vec::<f32>().unwrap(),
+            vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]
+        );
+
+        let mut buf = Vec::new();
+        list.write(&mut buf).unwrap();
+        assert_eq!(buf, std::fs::read(&path).unwrap());
+        std::fs::remove_file(&path).unwrap();
+
+        list.rename("a", "b");
+        let mut buf = Vec::new();
+        list.write(&mut buf).unwrap();
+        let loaded = ndarray::load_from_buffer(&buf).right().unwrap();
+        assert_eq!(
+            loaded["b"].to_vec::<f32>(),
+            vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]
+        );
+    }
+}
@@ -0,0 +1,539 @@
+//! Multi-dimensional indexing, see `nd_s!`.
+
+use super::NDArray;
+use crate::error::{Error, ErrorKind, Result};
+use crate::operator::{GetHandle, Operator, Tuple};
+use mxnet_sys::*;
+use std::ops::{Range, RangeFrom, RangeFull, RangeInclusive, RangeTo, RangeToInclusive};
+use std::ptr;
+
+/// Index of one axis. Negative indices count from the end.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SliceElem {
+    /// A single position, removing the axis.
+    Index(i64),
+    /// `start..end` by `step`, `None` for an open end. A negative step walks
+    /// backwards from `start`.
+    Slice {
+        start: Option<i64>,
+        end: Option<i64>,
+        step: i64,
+    },
+    /// A new axis of size 1.
+    NewAxis,
+}
+
+/// Marker for `SliceElem::NewAxis` in `nd_s!`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct NewAxis;
+
+impl SliceElem {
+    /// Set the step of a slice, like `..;2` in `nd_s!`.
+    pub fn step(self, step: i64) -> SliceElem {
+        match self {
+            SliceElem::Slice { start, end, .. } => SliceElem::Slice { start, end, step },
+            elem => elem,
+        }
+    }
+}
+
+impl From<NewAxis> for SliceElem {
+    fn from(_: NewAxis) -> SliceElem {
+        SliceElem::NewAxis
+    }
+}
+
+impl From<RangeFull> for SliceElem {
+    fn from(_: RangeFull) -> SliceElem {
+        SliceElem::Slice {
+            start: None,
+            end: None,
+            step: 1,
+        }
+    }
+}
+
+macro_rules! slice_elem_from {
+    ($($t:ty),*) => {
+        $(
+            impl From<$t> for SliceElem {
+                fn from(index: $t) -> SliceElem {
+                    SliceElem::Index(index as i64)
+                }
+            }
+
+            impl From<Range<$t>> for SliceElem {
+                fn from(range: Range<$t>) -> SliceElem {
+                    SliceElem::Slice {
+                        start: Some(range.start as i64),
+                        end: Some(range.end as i64),
+                        step: 1,
+                    }
+                }
+            }
+
+            impl From<RangeFrom<$t>> for SliceElem {
+                fn from(range: RangeFrom<$t>) -> SliceElem {
+                    SliceElem::Slice {
+                        start: Some(range.start as i64),
+                        end: None,
+                        step: 1,
+                    }
+                }
+            }
+
+            impl From<RangeTo<$t>> for SliceElem {
+                fn from(range: RangeTo<$t>) -> SliceElem {
+                    SliceElem::Slice {
+                        start: None,
+                        end: Some(range.end as i64),
+                        step: 1,
+                    }
+                }
+            }
+
+            // `..=-1` includes the last element, so it has no end.
+            impl From<RangeInclusive<$t>> for SliceElem {
+                fn from(range: RangeInclusive<$t>) -> SliceElem {
+                    let end = *range.end() as i64 + 1;
+                    SliceElem::Slice {
+                        start: Some(*range.start() as i64),
+                        end: Some(end).filter(|end| *end != 0),
+                        step: 1,
+                    }
+                }
+            }
+
+            impl From<RangeToInclusive<$t>> for SliceElem {
+                fn from(range: RangeToInclusive<$t>) -> SliceElem {
+                    let end = range.end as i64 + 1;
+                    SliceElem::Slice {
+                        start: None,
+                        end: Some(end).filter(|end| *end != 0),
+                        step: 1,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+slice_elem_from!(i32, i64, isize, usize);
+
+/// Build an index for `NDArray::index` and `NDArray::assign`, in the style
+/// of `ndarray::s!`. Each element is an integer, a range with an optional
+/// `;step`, or `NewAxis`. Axes left out are taken whole.
+///
+/// ```ignore
+/// // Rows from 1 on, every other column, then position 3 of the last axis.
+/// a.index(nd_s![1.., ..;2, 3]);
+/// // The last row, reversed, as a column.
+/// a.index(nd_s![-1, ..;-1, NewAxis]);
+/// ```
+#[macro_export]
+macro_rules! nd_s {
+    (@parse [$($out:expr,)*]) => {
+        &[$($out),*][..]
+    };
+    (@parse [$($out:expr,)*] $r:expr; $step:expr) => {
+        $crate::nd_s!(@parse [$($out,)* $crate::ndarray::SliceElem::from($r).step($step),])
+    };
+    (@parse [$($out:expr,)*] $r:expr; $step:expr, $($rest:tt)*) => {
+        $crate::nd_s!(@parse [$($out,)* $crate::ndarray::SliceElem::from($r).step($step),] $($rest)*)
+    };
+    (@parse [$($out:expr,)*] $r:expr) => {
+        $crate::nd_s!(@parse [$($out,)* $crate::ndarray::SliceElem::from($r),])
+    };
+    (@parse [$($out:expr,)*] $r:expr, $($rest:tt)*) => {
+        $crate::nd_s!(@parse [$($out,)* $crate::ndarray::SliceElem::from($r),] $($rest)*)
+    };
+    ($($t:tt)*) => {
+        $crate::nd_s!(@parse [] $($t)*)
+    };
+}
+
+/// A `SliceElem` checked against the size of its axis.
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Resolved {
+    Index(i64),
+    // `end` of -1 is before the first element, for negative steps.
+    Slice {
+        begin: i64,
+        end: i64,
+        step: i64,
+        len: i64,
+    },
+    NewAxis,
+}
+
+impl Resolved {
+    fn is_whole(&self, dim: i64) -> bool {
+        matches!(*self, Resolved::Slice { begin: 0, end, step: 1, .. } if end == dim)
+    }
+}
+
+fn resolve(shape: &[u32], index: &[SliceElem]) -> Result<Vec<Resolved>> {
+    let error = |message: String| {
+        Error::new(
+            ErrorKind::InvalidParameter,
+            format!("cannot index {:?} with {:?}: {}", shape, index, message),
+        )
+    };
+
+    let axes = index
+        .iter()
+        .filter(|elem| **elem != SliceElem::NewAxis)
+        .count();
+    if axes > shape.len() {
+        return Err(error(format!("too many indices for {} axes", shape.len())));
+    }
+
+    let mut resolved = Vec::with_capacity(index.len());
+    let mut dims = shape.iter().map(|dim| *dim as i64);
+    for elem in index {
+        let elem = match *elem {
+            SliceElem::NewAxis => Resolved::NewAxis,
+            SliceElem::Index(i) => {
+                let dim = dims.next().unwrap();
+                let index = if i < 0 { i + dim } else { i };
+                if index < 0 || index >= dim {
+                    return Err(error(format!("index {} is out of range for {}", i, dim)));
+                }
+                Resolved::Index(index)
+            }
+            SliceElem::Slice { start, end, step } => {
+                let dim = dims.next().unwrap();
+                if step == 0 {
+                    return Err(error("step is 0".to_owned()));
+                }
+                // Same as Python's `slice.indices`.
+                let (lower, upper) = if step > 0 { (0, dim) } else { (-1, dim - 1) };
+                let clamp = |i: i64| {
+                    let i = if i < 0 { i + dim } else { i };
+                    i.max(lower).min(upper)
+                };
+                let begin = start.map_or(if step > 0 { lower } else { upper }, clamp);
+                let end = end.map_or(if step > 0 { upper } else { lower }, clamp);
+                // Empty, like `2..2`, when the end is already behind begin.
+                let len = if step > 0 {
+                    (end - begin + step - 1) / step
+                } else {
+                    (begin - end - step - 1) / -step
+                }
+                .max(0);
+                Resolved::Slice {
+                    begin,
+                    end,
+                    step,
+                    len,
+                }
+            }
+        };
+        resolved.push(elem);
+    }
+    Ok(resolved)
+}
+
+/// Shape of the indexed array. MXNet has no 0-d arrays, a single element
+/// is `[1]`.
+fn indexed_shape(shape: &[u32], resolved: &[Resolved]) -> Vec<i64> {
+    let mut dims = Vec::with_capacity(shape.len() + resolved.len());
+    let mut axis = 0;
+    for elem in resolved {
+        match elem {
+            Resolved::Index(_) => axis += 1,
+            Resolved::Slice { len, .. } => {
+                dims.push(*len);
+                axis += 1;
+            }
+            Resolved::NewAxis => dims.push(1),
+        }
+    }
+    dims.extend(shape[axis..].iter().map(|dim| *dim as i64));
+    if dims.is_empty() {
+        dims.push(1);
+    }
+    dims
+}
+
+/// Shape of the region `_slice_assign` writes, indexed axes kept as 1.
+fn region_shape(shape: &[u32], resolved: &[Resolved]) -> Vec<i64> {
+    let mut region: Vec<i64> = resolved
+        .iter()
+        .filter_map(|elem| match elem {
+            Resolved::Index(_) => Some(1),
+            Resolved::Slice { len, .. } => Some(*len),
+            Resolved::NewAxis => None,
+        })
+        .collect();
+    region.extend(shape[region.len()..].iter().map(|dim| *dim as i64));
+    region
+}
+
+/// `begin`, `end` and `step` params of the `slice` operators, indices
+/// taking a region of size 1.
+fn slice_params(resolved: &[Resolved]) -> (Tuple<String>, Tuple<String>, Tuple<String>) {
+    let mut begin = Vec::new();
+    let mut end = Vec::new();
+    let mut step = Vec::new();
+    for elem in resolved {
+        match *elem {
+            Resolved::Index(i) => {
+                begin.push(i.to_string());
+                end.push((i + 1).to_string());
+                step.push("1".to_owned());
+            }
+            Resolved::Slice {
+                begin: b,
+                end: e,
+                step: s,
+                ..
+            } => {
+                begin.push(b.to_string());
+                end.push(if e < 0 {
+                    "None".to_owned()
+                } else {
+                    e.to_string()
+                });
+                step.push(s.to_string());
+            }
+            Resolved::NewAxis => {}
+        }
+    }
+    (begin.into(), end.into(), step.into())
+}
+
+impl NDArray {
+    /// Index with `nd_s!`. Leading integers and a following contiguous
+    /// range give a view sharing memory with self, anything else copies
+    /// through the `slice` operator. Every index `nd_s!` can express is a
+    /// strided region, which `slice` covers, so `take` is never needed.
+    /// An empty range like `2..2` gives an empty array.
+    pub fn index(&self, index: &[SliceElem]) -> NDArray {
+        self.try_index(index).unwrap()
+    }
+
+    pub fn try_index(&self, index: &[SliceElem]) -> Result<NDArray> {
+        let shape = self.try_shape()?;
+        let resolved = resolve(&shape, index)?;
+        let new_shape = indexed_shape(&shape, &resolved);
+        // MXNet can't slice out nothing, so build the empty result directly.
+        if new_shape.contains(&0) {
+            let dims: Vec<u32> = new_shape.iter().map(|dim| *dim as u32).collect();
+            return NDArray::builder()
+                .shape(&dims)
+                .context(self.try_context()?)
+                .dtype(self.try_dtype()?)
+                .try_create();
+        }
+
+        let leading = resolved
+            .iter()
+            .take_while(|elem| matches!(elem, Resolved::Index(_)))
+            .count();
+        let mut rest = resolved[leading..]
+            .iter()
+            .filter(|elem| **elem != Resolved::NewAxis);
+        let first = rest.next();
+        let view = matches!(first, None | Some(Resolved::Slice { step: 1, .. }))
+            && rest
+                .zip(shape.get(leading + 1..).unwrap_or(&[]))
+                .all(|(elem, dim)| elem.is_whole(*dim as i64));
+
+        let array = if view {
+            let mut array = self.clone();
+            for elem in &resolved[..leading] {
+                if let Resolved::Index(i) = elem {
+                    array = array.try_at(*i as u32)?;
+                }
+            }
+            match first {
+                Some(&Resolved::Slice { begin, end, .. })
+                    if begin != 0 || end != shape[leading] as i64 =>
+                {
+                    array.try_slice(begin as u32, end as u32)?
+                }
+                _ => array,
+            }
+        } else {
+            let (begin, end, step) = slice_params(&resolved);
+            Operator::try_new("slice")?
+                .push_input(self)
+                .try_set_param("begin", &begin)?
+                .try_set_param("end", &end)?
+                .try_set_param("step", &step)?
+                .try_invoke()?
+        };
+
+        let dims: Vec<i64> = array.try_shape()?.iter().map(|dim| *dim as i64).collect();
+        if dims == new_shape {
+            Ok(array)
+        } else {
+            array.try_reshape_view(&new_shape)
+        }
+    }
+
+    /// Write `value` into the region selected by `index`. `value` must have
+    /// as many elements as the region, `NewAxis` is ignored.
+    pub fn assign(&mut self, index: &[SliceElem], value: &NDArray) {
+        self.try_assign(index, value).unwrap()
+    }
+
+    pub fn try_assign(&mut self, index: &[SliceElem], value: &NDArray) -> Result<()> {
+        let shape = self.try_shape()?;
+        let resolved = resolve(&shape, index)?;
+        let region = region_shape(&shape, &resolved);
+
+        let value_shape = value.try_shape()?;
+        let size = |shape: &[i64]| shape.iter().product::<i64>();
+        let value_size = value_shape.iter().map(|dim| *dim as i64).product::<i64>();
+        if value_size != size(&region) {
+            return Err(Error::new(
+                ErrorKind::ShapeMismatch,
+                format!(
+                    "cannot assign {:?} to a region of shape {:?}",
+                    value_shape, region
+                ),
+            ));
+        }
+        if value_size == 0 {
+            return Ok(());
+        }
+        let value = value.try_reshape_view(&region)?;
+
+        let (begin, end, step) = slice_params(&resolved);
+        Operator::try_new("_slice_assign")?
+            .push_input(self)
+            .push_input(&value)
+            .try_set_param("begin", &begin)?
+            .try_set_param("end", &end)?
+            .try_set_param("step", &step)?
+            .try_invoke_with(self)
+    }
+
+    /// Fill the region selected by `index` with `value`.
+    pub fn assign_scalar(&mut self, index: &[SliceElem], value: f64) {
+        self.try_assign_scalar(index, value).unwrap()
+    }
+
+    pub fn try_assign_scalar(&mut self, index: &[SliceElem], value: f64) -> Result<()> {
+        let shape = self.try_shape()?;
+        let resolved = resolve(&shape, index)?;
+        if region_shape(&shape, &resolved).contains(&0) {
+            return Ok(());
+        }
+        let (begin, end, step) = slice_params(&resolved);
+        Operator::try_new("_slice_assign_scalar")?
+            .push_input(self)
+            .try_set_param("scalar", &value)?
+            .try_set_param("begin", &begin)?
+            .try_set_param("end", &end)?
+            .try_set_param("step", &step)?
+            .try_invoke_with(self)
+    }
+
+    /// The element at `index` of the first axis, a view.
+    pub fn at(&self, index: u32) -> NDArray {
+        self.try_at(index).unwrap()
+    }
+
+    pub fn try_at(&self, index: u32) -> Result<NDArray> {
+        let mut handle = ptr::null_mut();
+        try_call!(MXNDArrayAt(self.handle(), index, &mut handle))?;
+        Ok(NDArray::from(handle))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn macro_elems() {
+        assert_eq!(
+            nd_s![1.., ..;2, 3, NewAxis, ..=-1, -2..;-1],
+            &[
+                SliceElem::Slice {
+                    start: Some(1),
+                    end: None,
+                    step: 1
+                },
+                SliceElem::Slice {
+                    start: None,
+                    end: None,
+                    step: 2
+                },
+                SliceElem::Index(3),
+                SliceElem::NewAxis,
+                SliceElem::Slice {
+                    start: None,
+                    end: None,
+                    step: 1
+                },
+                SliceElem::Slice {
+                    start: Some(-2),
+                    end: None,
+                    step: -1
+                },
+            ][..]
+        );
+    }
+
+    #[test]
+    fn index() {
+        let data: Vec<f32> = (0..24).map(|x| x as f32).collect();
+        let a = NDArray::builder().data(&data).shape(&[2, 3, 4]).create();
+
+        // Views.
+        let b = a.index(nd_s![1]);
+        assert_eq!(b.shape(), vec![3, 4]);
+        assert_eq!(b.to_vec::<f32>(), data[12..]);
+        let b = a.index(nd_s![1, 1..]);
+        assert_eq!(b.shape(), vec![2, 4]);
+        assert_eq!(b.to_vec::<f32>(), data[16..]);
+        assert_eq!(a.index(nd_s![-1, -1, -1]).to_vec::<f32>(), vec![23.0]);
+
+        // Copies.
+        let b = a.index(nd_s![.., 0, ..;2]);
+        assert_eq!(b.shape(), vec![2, 2]);
+        assert_eq!(b.to_vec::<f32>(), vec![0.0, 2.0, 12.0, 14.0]);
+        let b = a.index(nd_s![0, 0, ..;-1]);
+        assert_eq!(b.to_vec::<f32>(), vec![3.0, 2.0, 1.0, 0.0]);
+        let b = a.index(nd_s![0, NewAxis, .., 1..3]);
+        assert_eq!(b.shape(), vec![1, 3, 2]);
+
+        // Empty ranges.
+        let b = a.index(nd_s![2..2]);
+        assert_eq!(b.shape(), vec![0, 3, 4]);
+        assert_eq!(b.dtype(), a.dtype());
+        assert_eq!(a.index(nd_s![.., -1..1]).shape(), vec![2, 0, 4]);
+        assert_eq!(a.index(nd_s![0, 0, 0..2;-1, NewAxis]).shape(), vec![0, 1]);
+
+        assert!(a.try_index(nd_s![2]).is_err());
+        assert!(a.try_index(nd_s![0, 0, 0, 0]).is_err());
+        assert!(a.try_index(nd_s![..;0]).is_err());
+    }
+
+    #[test]
+    fn assign() {
+        let mut a = NDArray::builder()
+            .data(&[0.0f32; 6])
+            .shape(&[2, 3])
+            .create();
+        a.assign_scalar(nd_s![.., ..;2], 1.0);
+        assert_eq!(a.to_vec::<f32>(), vec![1.0, 0.0, 1.0, 1.0, 0.0, 1.0]);
+
+        let row = NDArray::builder().data(&[7.0f32, 8.0, 9.0]).create();
+        a.assign(nd_s![-1], &row);
+        assert_eq!(a.to_vec::<f32>(), vec![1.0, 0.0, 1.0, 7.0, 8.0, 9.0]);
+
+        let col = NDArray::builder().data(&[5.0f32, 6.0]).create();
+        a.assign(nd_s![.., 1], &col);
+        assert_eq!(a.to_vec::<f32>(), vec![1.0, 5.0, 1.0, 7.0, 6.0, 9.0]);
+
+        assert!(a.try_assign(nd_s![0], &col).is_err());
+        let before = a.to_vec::<f32>();
+        a.assign_scalar(nd_s![1..1], 3.0);
+        assert_eq!(a.to_vec::<f32>(), before);
+    }
+}
@@ -0,0 +1,199 @@
+//! Elementwise math and comparisons. Comparisons return masks of 1 and 0
+//! in the dtype of the inputs. Ops on two arrays broadcast like the
+//! arithmetic operators.
+
+use super::NDArray;
+use crate::error::{Error, ErrorKind, Result};
+use crate::operator::Operator;
+
+macro_rules! unary {
+    ($($(#[$doc:meta])* $name:ident, $try_name:ident => $op_name:expr;)*) => {
+        impl NDArray {
+            $(
+                $(#[$doc])*
+                pub fn $name(&self) -> NDArray {
+                    self.$try_name().unwrap()
+                }
+
+                pub fn $try_name(&self) -> Result<NDArray> {
+                    Operator::try_new($op_name)?.push_input(self).try_invoke()
+                }
+            )*
+        }
+    };
+}
+
+// Each op has an array form going through the `broadcast_*` op, and a
+// scalar form.
+macro_rules! binary {
+    ($(
+        $(#[$doc:meta])*
+        $name:ident, $try_name:ident => $op_name:expr;
+        $(#[$scalar_doc:meta])*
+        $scalar_name:ident, $try_scalar_name:ident => $scalar_op_name:expr;
+    )*) => {
+        impl NDArray {
+            $(
+                $(#[$doc])*
+                pub fn $name(&self, other: &NDArray) -> NDArray {
+                    self.$try_name(other).unwrap()
+                }
+
+                pub fn $try_name(&self, other: &NDArray) -> Result<NDArray> {
+                    Operator::try_new($op_name)?
+                        .push_input(self)
+                        .push_input(other)
+                        .try_invoke()
+                }
+
+                $(#[$scalar_doc])*
+                pub fn $scalar_name(&self, scalar: f32) -> NDArray {
+                    self.$try_scalar_name(scalar).unwrap()
+                }
+
+                pub fn $try_scalar_name(&self, scalar: f32) -> Result<NDArray> {
+                    Operator::try_new($scalar_op_name)?
+                        .push_input(self)
+                        .try_set_param("scalar", &scalar)?
+                        .try_invoke()
+                }
+            )*
+        }
+    };
+}
+
+unary! {
+    /// `e` to the power of each element.
+    exp, try_exp => "exp";
+    /// Natural logarithm.
+    log, try_log => "log";
+    /// Square root.
+    sqrt, try_sqrt => "sqrt";
+    /// Absolute value.
+    abs, try_abs => "abs";
+    /// `1 / (1 + exp(-x))`.
+    sigmoid, try_sigmoid => "sigmoid";
+    /// Hyperbolic tangent.
+    tanh, try_tanh => "tanh";
+    /// `max(x, 0)`.
+    relu, try_relu => "relu";
+    /// -1, 0 or 1 by the sign of each element.
+    sign, try_sign => "sign";
+    /// Round half away from zero.
+    round, try_round => "round";
+}
+
+binary! {
+    /// Raise each element to the power of `other`.
+    pow, try_pow => "broadcast_power";
+    /// Raise each element to the power of `scalar`.
+    pow_scalar, try_pow_scalar => "_power_scalar";
+    /// The larger of each pair of elements.
+    maximum, try_maximum => "broadcast_maximum";
+    /// The larger of each element and `scalar`.
+    maximum_scalar, try_maximum_scalar => "_maximum_scalar";
+    /// The smaller of each pair of elements.
+    minimum, try_minimum => "broadcast_minimum";
+    /// The smaller of each element and `scalar`.
+    minimum_scalar, try_minimum_scalar => "_minimum_scalar";
+    /// Mask of `self == other`.
+    eq, try_eq => "broadcast_equal";
+    /// Mask of `self == scalar`.
+    eq_scalar, try_eq_scalar => "_equal_scalar";
+    /// Mask of `self != other`.
+    ne, try_ne => "broadcast_not_equal";
+    /// Mask of `self != scalar`.
+    ne_scalar, try_ne_scalar => "_not_equal_scalar";
+    /// Mask of `self < other`.
+    lt, try_lt => "broadcast_lesser";
+    /// Mask of `self < scalar`.
+    lt_scalar, try_lt_scalar => "_lesser_scalar";
+    /// Mask of `self > other`.
+    gt, try_gt => "broadcast_greater";
+    /// Mask of `self > scalar`.
+    gt_scalar, try_gt_scalar => "_greater_scalar";
+    /// Mask of `self <= other`.
+    le, try_le => "broadcast_lesser_equal";
+    /// Mask of `self <= scalar`.
+    le_scalar, try_le_scalar => "_lesser_equal_scalar";
+    /// Mask of `self >= other`.
+    ge, try_ge => "broadcast_greater_equal";
+    /// Mask of `self >= scalar`.
+    ge_scalar, try_ge_scalar => "_greater_equal_scalar";
+}
+
+impl NDArray {
+    /// Limit the elements to `[a_min, a_max]`.
+    pub fn clip(&self, a_min: f32, a_max: f32) -> NDArray {
+        self.try_clip(a_min, a_max).unwrap()
+    }
+
+    pub fn try_clip(&self, a_min: f32, a_max: f32) -> Result<NDArray> {
+        if a_min > a_max {
+            return Err(Error::new(
+                ErrorKind::InvalidParameter,
+                format!("clip needs a_min <= a_max, got {} and {}", a_min, a_max),
+            ));
+        }
+        Operator::try_new("clip")?
+            .push_input(self)
+            .try_set_param("a_min", &a_min)?
+            .try_set_param("a_max", &a_max)?
+            .try_invoke()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn array(data: &[f32]) -> NDArray {
+        NDArray::builder().data(data).create()
+    }
+
+    #[test]
+    fn unary() {
+        let a = array(&[-1.5, 0.0, 4.0]);
+        assert_eq!(a.abs().to_vec::<f32>(), vec![1.5, 0.0, 4.0]);
+        assert_eq!(a.relu().to_vec::<f32>(), vec![0.0, 0.0, 4.0]);
+        assert_eq!(a.sign().to_vec::<f32>(), vec![-1.0, 0.0, 1.0]);
+        assert_eq!(a.round().to_vec::<f32>(), vec![-2.0, 0.0, 4.0]);
+        assert_eq!(a.clip(-1.0, 1.0).to_vec::<f32>(), vec![-1.0, 0.0, 1.0]);
+        assert!(a.try_clip(1.0, -1.0).is_err());
+        assert_eq!(a.relu().sqrt().to_vec::<f32>(), vec![0.0, 0.0, 2.0]);
+        assert_eq!(array(&[0.0]).exp().to_vec::<f32>(), vec![1.0]);
+        assert_eq!(array(&[1.0]).log().to_vec::<f32>(), vec![0.0]);
+        assert_eq!(array(&[0.0]).sigmoid().to_vec::<f32>(), vec![0.5]);
+        assert_eq!(array(&[0.0]).tanh().to_vec::<f32>(), vec![0.0]);
+    }
+
+    #[test]
+    fn binary() {
+        let a = array(&[1.0, 2.0, 3.0]);
+        let b = array(&[3.0, 2.0, 1.0]);
+        assert_eq!(a.pow(&b).to_vec::<f32>(), vec![1.0, 4.0, 3.0]);
+        assert_eq!(a.pow_scalar(2.0).to_vec::<f32>(), vec![1.0, 4.0, 9.0]);
+        assert_eq!(a.maximum(&b).to_vec::<f32>(), vec![3.0, 2.0, 3.0]);
+        assert_eq!(a.minimum_scalar(2.0).to_vec::<f32>(), vec![1.0, 2.0, 2.0]);
+
+        assert_eq!(a.eq(&b).to_vec::<f32>(), vec![0.0, 1.0, 0.0]);
+        assert_eq!(a.ne(&b).to_vec::<f32>(), vec![1.0, 0.0, 1.0]);
+        assert_eq!(a.lt(&b).to_vec::<f32>(), vec![1.0, 0.0, 0.0]);
+        assert_eq!(a.ge(&b).to_vec::<f32>(), vec![0.0, 1.0, 1.0]);
+        assert_eq!(a.gt_scalar(1.0).to_vec::<f32>(), vec![0.0, 1.0, 1.0]);
+        assert_eq!(a.le_scalar(2.0).to_vec::<f32>(), vec![1.0, 1.0, 0.0]);
+
+        // Broadcasting.
+        assert_eq!(a.eq(&array(&[2.0])).to_vec::<f32>(), vec![0.0, 1.0, 0.0]);
+        let col = NDArray::builder()
+            .data(&[1.0f32, 2.0])
+            .shape(&[2, 1])
+            .create();
+        let max = a.maximum(&col);
+        assert_eq!(max.shape(), vec![2, 3]);
+        assert_eq!(max.to_vec::<f32>(), vec![1.0, 2.0, 3.0, 2.0, 2.0, 3.0]);
+
+        let err = a.try_eq(&array(&[1.0, 2.0])).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::ShapeMismatch);
+    }
+}
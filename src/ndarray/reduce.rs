@@ -0,0 +1,311 @@
+//! Reductions, arg-reductions and sorting.
+
+use super::shape::{normalize_axes, normalize_axis, to_shape};
+use super::{Element, NDArray};
+use crate::error::{Error, ErrorKind, Result};
+use crate::operator::Operator;
+
+/// The axes a reduction runs over, all of them if empty.
+///
+/// ```ignore
+/// a.sum(1);
+/// a.sum([0, -1]);
+/// a.mean(Axes::new(&[1]).keepdims(true));
+/// a.max(Axes::all());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Axes {
+    axes: Vec<i32>,
+    keepdims: bool,
+    exclude: bool,
+}
+
+impl Axes {
+    pub fn new(axes: &[i32]) -> Axes {
+        Axes {
+            axes: axes.to_vec(),
+            ..Default::default()
+        }
+    }
+
+    pub fn all() -> Axes {
+        Axes::default()
+    }
+
+    /// Keep the reduced axes with size 1.
+    pub fn keepdims(mut self, keepdims: bool) -> Axes {
+        self.keepdims = keepdims;
+        self
+    }
+
+    /// Reduce over every axis but the given ones.
+    pub fn exclude(mut self, exclude: bool) -> Axes {
+        self.exclude = exclude;
+        self
+    }
+}
+
+impl From<i32> for Axes {
+    fn from(axis: i32) -> Axes {
+        Axes::new(&[axis])
+    }
+}
+
+impl<'a> From<&'a [i32]> for Axes {
+    fn from(axes: &'a [i32]) -> Axes {
+        Axes::new(axes)
+    }
+}
+
+impl<const N: usize> From<[i32; N]> for Axes {
+    fn from(axes: [i32; N]) -> Axes {
+        Axes::new(&axes)
+    }
+}
+
+impl NDArray {
+    pub fn sum(&self, axes: impl Into<Axes>) -> NDArray {
+        self.try_sum(axes).unwrap()
+    }
+
+    pub fn try_sum(&self, axes: impl Into<Axes>) -> Result<NDArray> {
+        self.try_reduce("sum", &axes.into())?.try_invoke()
+    }
+
+    pub fn mean(&self, axes: impl Into<Axes>) -> NDArray {
+        self.try_mean(axes).unwrap()
+    }
+
+    pub fn try_mean(&self, axes: impl Into<Axes>) -> Result<NDArray> {
+        self.try_reduce("mean", &axes.into())?.try_invoke()
+    }
+
+    pub fn max(&self, axes: impl Into<Axes>) -> NDArray {
+        self.try_max(axes).unwrap()
+    }
+
+    pub fn try_max(&self, axes: impl Into<Axes>) -> Result<NDArray> {
+        self.try_reduce("max", &axes.into())?.try_invoke()
+    }
+
+    pub fn min(&self, axes: impl Into<Axes>) -> NDArray {
+        self.try_min(axes).unwrap()
+    }
+
+    pub fn try_min(&self, axes: impl Into<Axes>) -> Result<NDArray> {
+        self.try_reduce("min", &axes.into())?.try_invoke()
+    }
+
+    pub fn prod(&self, axes: impl Into<Axes>) -> NDArray {
+        self.try_prod(axes).unwrap()
+    }
+
+    pub fn try_prod(&self, axes: impl Into<Axes>) -> Result<NDArray> {
+        self.try_reduce("prod", &axes.into())?.try_invoke()
+    }
+
+    /// Sum, treating NaN as 0.
+    pub fn nansum(&self, axes: impl Into<Axes>) -> NDArray {
+        self.try_nansum(axes).unwrap()
+    }
+
+    pub fn try_nansum(&self, axes: impl Into<Axes>) -> Result<NDArray> {
+        self.try_reduce("nansum", &axes.into())?.try_invoke()
+    }
+
+    /// The L1 (`ord` 1) or L2 (`ord` 2) norm. `exclude` isn't supported.
+    pub fn norm(&self, ord: i32, axes: impl Into<Axes>) -> NDArray {
+        self.try_norm(ord, axes).unwrap()
+    }
+
+    pub fn try_norm(&self, ord: i32, axes: impl Into<Axes>) -> Result<NDArray> {
+        let axes = axes.into();
+        if ord != 1 && ord != 2 {
+            return Err(Error::new(
+                ErrorKind::InvalidParameter,
+                format!("norm supports ord 1 and 2, not {}", ord),
+            ));
+        }
+        if axes.exclude {
+            return Err(Error::new(
+                ErrorKind::InvalidParameter,
+                "norm doesn't support exclude",
+            ));
+        }
+        let mut op = Operator::try_new("norm")?;
+        op.try_set_param("ord", &ord)?;
+        self.try_set_axes(&mut op, &axes)?;
+        op.push_input(self).try_invoke()
+    }
+
+    /// Indices of the maximum along `axis`, or of the flattened array.
+    pub fn argmax(&self, axis: Option<i32>, keepdims: bool) -> NDArray {
+        self.try_argmax(axis, keepdims).unwrap()
+    }
+
+    pub fn try_argmax(&self, axis: Option<i32>, keepdims: bool) -> Result<NDArray> {
+        self.try_arg_reduce("argmax", axis, keepdims)
+    }
+
+    /// Indices of the minimum along `axis`, or of the flattened array.
+    pub fn argmin(&self, axis: Option<i32>, keepdims: bool) -> NDArray {
+        self.try_argmin(axis, keepdims).unwrap()
+    }
+
+    pub fn try_argmin(&self, axis: Option<i32>, keepdims: bool) -> Result<NDArray> {
+        self.try_arg_reduce("argmin", axis, keepdims)
+    }
+
+    /// The `k` largest values along `axis` and their indices, or the
+    /// smallest with `is_ascend`.
+    pub fn topk(&self, axis: i32, k: u32, is_ascend: bool) -> (NDArray, NDArray) {
+        self.try_topk(axis, k, is_ascend).unwrap()
+    }
+
+    pub fn try_topk(&self, axis: i32, k: u32, is_ascend: bool) -> Result<(NDArray, NDArray)> {
+        let shape = self.try_shape()?;
+        let axis = normalize_axis(axis, shape.len())?;
+        if k == 0 || k > shape[axis] {
+            return Err(Error::new(
+                ErrorKind::InvalidParameter,
+                format!(
+                    "k is {}, but axis {} of {:?} has size {}",
+                    k, axis, shape, shape[axis]
+                ),
+            ));
+        }
+        let [values, indices] = Operator::try_new("topk")?
+            .push_input(self)
+            .try_set_param("axis", &axis)?
+            .try_set_param("k", &k)?
+            .try_set_param("ret_typ", &"both")?
+            .try_set_param("is_ascend", &is_ascend)?
+            .try_invoke_n()?;
+        Ok((values, indices))
+    }
+
+    pub fn sort(&self, axis: i32, is_ascend: bool) -> NDArray {
+        self.try_sort(axis, is_ascend).unwrap()
+    }
+
+    pub fn try_sort(&self, axis: i32, is_ascend: bool) -> Result<NDArray> {
+        self.try_sort_with("sort", axis, is_ascend)
+    }
+
+    /// Indices that would sort the array along `axis`.
+    pub fn argsort(&self, axis: i32, is_ascend: bool) -> NDArray {
+        self.try_argsort(axis, is_ascend).unwrap()
+    }
+
+    pub fn try_argsort(&self, axis: i32, is_ascend: bool) -> Result<NDArray> {
+        self.try_sort_with("argsort", axis, is_ascend)
+    }
+
+    /// The value of a 1-element array, e.g. the result of a full reduction.
+    pub fn scalar<T: Element>(&self) -> T {
+        self.try_scalar().unwrap()
+    }
+
+    pub fn try_scalar<T: Element>(&self) -> Result<T> {
+        let shape = self.try_shape()?;
+        if shape.iter().product::<u32>() != 1 {
+            return Err(Error::new(
+                ErrorKind::ShapeMismatch,
+                format!("scalar needs a 1-element array, got shape {:?}", shape),
+            ));
+        }
+        Ok(self.try_to_vec()?[0])
+    }
+
+    fn try_reduce(&self, op_name: &str, axes: &Axes) -> Result<Operator> {
+        let mut op = Operator::try_new(op_name)?;
+        self.try_set_axes(&mut op, axes)?;
+        op.try_set_param("exclude", &axes.exclude)?.push_input(self);
+        Ok(op)
+    }
+
+    fn try_set_axes(&self, op: &mut Operator, axes: &Axes) -> Result<()> {
+        if !axes.axes.is_empty() {
+            let ndim = self.try_shape()?.len();
+            op.try_set_param("axis", &to_shape(&normalize_axes(&axes.axes, ndim)?))?;
+        }
+        op.try_set_param("keepdims", &axes.keepdims)?;
+        Ok(())
+    }
+
+    fn try_arg_reduce(&self, op_name: &str, axis: Option<i32>, keepdims: bool) -> Result<NDArray> {
+        let mut op = Operator::try_new(op_name)?;
+        if let Some(axis) = axis {
+            let ndim = self.try_shape()?.len();
+            op.try_set_param("axis", &normalize_axis(axis, ndim)?)?;
+        }
+        op.push_input(self)
+            .try_set_param("keepdims", &keepdims)?
+            .try_invoke()
+    }
+
+    fn try_sort_with(&self, op_name: &str, axis: i32, is_ascend: bool) -> Result<NDArray> {
+        let ndim = self.try_shape()?.len();
+        Operator::try_new(op_name)?
+            .push_input(self)
+            .try_set_param("axis", &normalize_axis(axis, ndim)?)?
+            .try_set_param("is_ascend", &is_ascend)?
+            .try_invoke()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reduce() {
+        let a = NDArray::builder()
+            .data(&[1.0f32, 2.0, 3.0, 4.0, 5.0, 6.0])
+            .shape(&[2, 3])
+            .create();
+
+        assert_eq!(a.sum(Axes::all()).scalar::<f32>(), 21.0);
+        assert_eq!(a.sum(0).to_vec::<f32>(), vec![5.0, 7.0, 9.0]);
+        assert_eq!(a.sum(Axes::new(&[1]).keepdims(true)).shape(), vec![2, 1]);
+        assert_eq!(
+            a.sum(Axes::new(&[1]).exclude(true)).to_vec::<f32>(),
+            vec![5.0, 7.0, 9.0]
+        );
+        assert_eq!(a.mean(-1).to_vec::<f32>(), vec![2.0, 5.0]);
+        assert_eq!(a.max([0, 1]).scalar::<f32>(), 6.0);
+        assert_eq!(a.min(1).to_vec::<f32>(), vec![1.0, 4.0]);
+        assert_eq!(a.prod(0).to_vec::<f32>(), vec![4.0, 10.0, 18.0]);
+        assert_eq!(a.nansum(Axes::all()).scalar::<f32>(), 21.0);
+        assert_eq!(a.norm(1, Axes::all()).scalar::<f32>(), 21.0);
+        assert!(a.try_norm(3, Axes::all()).is_err());
+        assert!(a.try_sum(2).is_err());
+        assert!(a.try_scalar::<f32>().is_err());
+    }
+
+    #[test]
+    fn arg_reduce_and_sort() {
+        let a = NDArray::builder()
+            .data(&[3.0f32, 1.0, 2.0, 0.0, 5.0, 4.0])
+            .shape(&[2, 3])
+            .create();
+
+        assert_eq!(a.argmax(Some(1), false).to_vec::<f32>(), vec![0.0, 1.0]);
+        assert_eq!(a.argmin(Some(-1), false).to_vec::<f32>(), vec![1.0, 0.0]);
+        assert_eq!(a.argmax(None, false).scalar::<f32>(), 4.0);
+
+        let (values, indices) = a.topk(1, 2, false);
+        assert_eq!(values.to_vec::<f32>(), vec![3.0, 2.0, 5.0, 4.0]);
+        assert_eq!(indices.to_vec::<f32>(), vec![0.0, 2.0, 1.0, 2.0]);
+        assert!(a.try_topk(1, 4, false).is_err());
+
+        assert_eq!(
+            a.sort(1, true).to_vec::<f32>(),
+            vec![1.0, 2.0, 3.0, 0.0, 4.0, 5.0]
+        );
+        assert_eq!(
+            a.argsort(0, false).to_vec::<f32>(),
+            vec![0.0, 1.0, 1.0, 1.0, 0.0, 0.0]
+        );
+    }
+}
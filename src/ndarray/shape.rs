@@ -0,0 +1,342 @@
+//! Shape manipulation. Shapes and axes are checked against `shape()` first,
+//! so mistakes come back as errors naming the shapes involved rather than
+//! an MXNet check failure.
+
+use super::NDArray;
+use crate::error::{Error, ErrorKind, Result};
+use crate::operator::{Operator, Shape};
+
+impl NDArray {
+    /// A view of the same data with a new shape. Besides positive sizes,
+    /// `shape` takes MXNet's special codes:
+    ///
+    /// - `0` copies the input dim, `-1` is inferred from the others.
+    /// - `-2` copies all the remaining input dims.
+    /// - `-3` merges two input dims.
+    /// - `-4, a, b` splits an input dim into `a` and `b`, one may be `-1`.
+    ///
+    /// ```ignore
+    /// // (2, 3, 4) => (6, 4)
+    /// a.reshape(&[-3, -2]);
+    /// // (2, 3, 4) => (2, 3, 2, 2)
+    /// a.reshape(&[0, 0, -4, 2, -1]);
+    /// ```
+    pub fn reshape(&self, shape: &[i64]) -> NDArray {
+        self.try_reshape(shape).unwrap()
+    }
+
+    pub fn try_reshape(&self, shape: &[i64]) -> Result<NDArray> {
+        let dims = infer_reshape(&self.try_shape()?, shape)?;
+        self.try_reshape_view(&dims)
+    }
+
+    /// Permute the axes, reversing them if `axes` is empty.
+    pub fn transpose(&self, axes: &[i32]) -> NDArray {
+        self.try_transpose(axes).unwrap()
+    }
+
+    pub fn try_transpose(&self, axes: &[i32]) -> Result<NDArray> {
+        let shape = self.try_shape()?;
+        let mut op = Operator::try_new("transpose")?;
+        if !axes.is_empty() {
+            let axes = normalize_axes(axes, shape.len())?;
+            let mut sorted = axes.clone();
+            sorted.sort_unstable();
+            if sorted.len() != shape.len() || sorted.iter().enumerate().any(|(i, a)| *a != i) {
+                return Err(invalid_axis(format!(
+                    "axes {:?} are not a permutation of the {} axes of {:?}",
+                    axes,
+                    shape.len(),
+                    shape
+                )));
+            }
+            op.try_set_param("axes", &to_shape(&axes))?;
+        }
+        op.push_input(self).try_invoke()
+    }
+
+    /// Insert an axis of size 1 at `axis`, which may be `ndim()`.
+    pub fn expand_dims(&self, axis: i32) -> NDArray {
+        self.try_expand_dims(axis).unwrap()
+    }
+
+    pub fn try_expand_dims(&self, axis: i32) -> Result<NDArray> {
+        let shape = self.try_shape()?;
+        let axis = normalize_axis(axis, shape.len() + 1)?;
+        Operator::try_new("expand_dims")?
+            .push_input(self)
+            .try_set_param("axis", &axis)?
+            .try_invoke()
+    }
+
+    /// Remove the given axes of size 1, or all of them if `axes` is empty.
+    pub fn squeeze(&self, axes: &[i32]) -> NDArray {
+        self.try_squeeze(axes).unwrap()
+    }
+
+    pub fn try_squeeze(&self, axes: &[i32]) -> Result<NDArray> {
+        let shape = self.try_shape()?;
+        let mut op = Operator::try_new("squeeze")?;
+        if !axes.is_empty() {
+            let axes = normalize_axes(axes, shape.len())?;
+            if let Some(axis) = axes.iter().find(|axis| shape[**axis] != 1) {
+                return Err(shape_mismatch(format!(
+                    "cannot squeeze axis {} of {:?}, its size is not 1",
+                    axis, shape
+                )));
+            }
+            op.try_set_param("axis", &to_shape(&axes))?;
+        }
+        op.push_input(self).try_invoke()
+    }
+
+    /// Collapse all the axes but the first, giving a 2-D array.
+    pub fn flatten(&self) -> NDArray {
+        self.try_flatten().unwrap()
+    }
+
+    pub fn try_flatten(&self) -> Result<NDArray> {
+        let shape = self.try_shape()?;
+        if shape.is_empty() {
+            return Err(shape_mismatch("cannot flatten a 0-d array"));
+        }
+        Operator::try_new("Flatten")?.push_input(self).try_invoke()
+    }
+
+    pub fn swapaxes(&self, axis1: i32, axis2: i32) -> NDArray {
+        self.try_swapaxes(axis1, axis2).unwrap()
+    }
+
+    pub fn try_swapaxes(&self, axis1: i32, axis2: i32) -> Result<NDArray> {
+        let ndim = self.try_shape()?.len();
+        Operator::try_new("SwapAxis")?
+            .push_input(self)
+            .try_set_param("dim1", &normalize_axis(axis1, ndim)?)?
+            .try_set_param("dim2", &normalize_axis(axis2, ndim)?)?
+            .try_invoke()
+    }
+
+    /// Repeat the axes of size 1 to match `shape`, which must have as many
+    /// axes. A size of 0 in `shape` keeps the input size.
+    pub fn broadcast_to(&self, shape: &[u32]) -> NDArray {
+        self.try_broadcast_to(shape).unwrap()
+    }
+
+    pub fn try_broadcast_to(&self, shape: &[u32]) -> Result<NDArray> {
+        let from = self.try_shape()?;
+        let compatible = from.len() == shape.len()
+            && from
+                .iter()
+                .zip(shape)
+                .all(|(from, to)| *to == 0 || from == to || *from == 1);
+        if !compatible {
+            return Err(shape_mismatch(format!(
+                "cannot broadcast {:?} to {:?}",
+                from, shape
+            )));
+        }
+        Operator::try_new("broadcast_to")?
+            .push_input(self)
+            .try_set_param("shape", &Shape::from(shape))?
+            .try_invoke()
+    }
+
+    /// Repeat the whole array `reps[i]` times along axis `i`. When `reps`
+    /// and the array have different numbers of axes, the shorter one is
+    /// padded with leading 1s.
+    pub fn tile(&self, reps: &[u32]) -> NDArray {
+        self.try_tile(reps).unwrap()
+    }
+
+    pub fn try_tile(&self, reps: &[u32]) -> Result<NDArray> {
+        if reps.is_empty() {
+            return Err(Error::new(
+                ErrorKind::InvalidParameter,
+                "tile needs at least one repeat count in reps, got none",
+            ));
+        }
+        Operator::try_new("tile")?
+            .push_input(self)
+            .try_set_param("reps", &Shape::from(reps))?
+            .try_invoke()
+    }
+}
+
+/// Resolve the special codes of `reshape` against the input shape `from`.
+fn infer_reshape(from: &[u32], codes: &[i64]) -> Result<Vec<i64>> {
+    let from: Vec<i64> = from.iter().map(|dim| *dim as i64).collect();
+    let error = |reason: &str| {
+        shape_mismatch(format!(
+            "cannot reshape {:?} to {:?}: {}",
+            from, codes, reason
+        ))
+    };
+    let input = |index: usize| {
+        from.get(index)
+            .copied()
+            .ok_or_else(|| error("too few input dims"))
+    };
+
+    let mut dims = Vec::with_capacity(codes.len());
+    let mut inferred = None;
+    let mut src = 0;
+    let mut i = 0;
+    while i < codes.len() {
+        match codes[i] {
+            0 => {
+                dims.push(input(src)?);
+                src += 1;
+            }
+            -1 => {
+                if inferred.is_some() {
+                    return Err(error("more than one -1"));
+                }
+                inferred = Some(dims.len());
+                dims.push(1);
+                src += 1;
+            }
+            -2 => {
+                dims.extend_from_slice(from.get(src..).unwrap_or(&[]));
+                src = from.len();
+            }
+            -3 => {
+                dims.push(input(src)? * input(src + 1)?);
+                src += 2;
+            }
+            -4 => {
+                let (a, b) = match codes.get(i + 1..i + 3) {
+                    Some(&[a, b]) => (a, b),
+                    _ => return Err(error("-4 takes two dims")),
+                };
+                let dim = input(src)?;
+                let (a, b) = match (a, b) {
+                    (-1, b) if b > 0 => (dim / b, b),
+                    (a, -1) if a > 0 => (a, dim / a),
+                    (a, b) => (a, b),
+                };
+                if a <= 0 || b <= 0 || a * b != dim {
+                    return Err(error(&format!("cannot split {} with -4", dim)));
+                }
+                dims.push(a);
+                dims.push(b);
+                src += 1;
+                i += 2;
+            }
+            dim if dim > 0 => {
+                dims.push(dim);
+                src += 1;
+            }
+            dim => return Err(error(&format!("unknown code {}", dim))),
+        }
+        i += 1;
+    }
+
+    let size: i64 = from.iter().product();
+    let known: i64 = dims.iter().product();
+    if let Some(index) = inferred {
+        if known == 0 || size % known != 0 {
+            return Err(error("cannot infer -1"));
+        }
+        dims[index] = size / known;
+    } else if known != size {
+        return Err(error("sizes differ"));
+    }
+    Ok(dims)
+}
+
+/// Map a possibly negative `axis` into `0..ndim`.
+pub(super) fn normalize_axis(axis: i32, ndim: usize) -> Result<usize> {
+    let index = if axis < 0 { axis + ndim as i32 } else { axis };
+    if index < 0 || index >= ndim as i32 {
+        return Err(invalid_axis(format!(
+            "axis {} is out of range for {} axes",
+            axis, ndim
+        )));
+    }
+    Ok(index as usize)
+}
+
+pub(super) fn normalize_axes(axes: &[i32], ndim: usize) -> Result<Vec<usize>> {
+    axes.iter()
+        .map(|axis| normalize_axis(*axis, ndim))
+        .collect()
+}
+
+pub(super) fn to_shape(axes: &[usize]) -> Shape {
+    axes.iter()
+        .map(|axis| *axis as i64)
+        .collect::<Vec<_>>()
+        .into()
+}
+
+fn invalid_axis(message: impl Into<String>) -> Error {
+    Error::new(ErrorKind::InvalidParameter, message)
+}
+
+fn shape_mismatch(message: impl Into<String>) -> Error {
+    Error::new(ErrorKind::ShapeMismatch, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+
+    #[test]
+    fn reshape_codes() {
+        let shape = [2, 3, 4];
+        assert_eq!(infer_reshape(&shape, &[6, -1]).unwrap(), vec![6, 4]);
+        assert_eq!(infer_reshape(&shape, &[0, -1]).unwrap(), vec![2, 12]);
+        assert_eq!(infer_reshape(&shape, &[-2, 1]).unwrap(), vec![2, 3, 4, 1]);
+        assert_eq!(infer_reshape(&shape, &[-3, -2]).unwrap(), vec![6, 4]);
+        assert_eq!(
+            infer_reshape(&shape, &[0, 0, -4, 2, -1]).unwrap(),
+            vec![2, 3, 2, 2]
+        );
+        assert!(infer_reshape(&shape, &[5, -1]).is_err());
+        assert!(infer_reshape(&shape, &[-1, -1]).is_err());
+        assert!(infer_reshape(&shape, &[0, 0, -4, 3, -1]).is_err());
+        assert!(infer_reshape(&shape, &[2, 3]).is_err());
+    }
+
+    #[test]
+    fn manipulate() {
+        let a = NDArray::builder()
+            .data(&[1.0f32, 2.0, 3.0, 4.0, 5.0, 6.0])
+            .shape(&[2, 3])
+            .create();
+
+        let r = a.reshape(&[-1, 2]);
+        assert_eq!(r.shape(), vec![3, 2]);
+        assert_eq!(r.to_vec::<f32>(), a.to_vec::<f32>());
+        let err = a.try_reshape(&[4, -1]).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::ShapeMismatch);
+
+        let t = a.transpose(&[]);
+        assert_eq!(t.shape(), vec![3, 2]);
+        assert_eq!(t.to_vec::<f32>(), vec![1.0, 4.0, 2.0, 5.0, 3.0, 6.0]);
+        assert_eq!(a.transpose(&[-1, 0]).shape(), vec![3, 2]);
+        assert!(a.try_transpose(&[0, 0]).is_err());
+        assert_eq!(a.swapaxes(0, -1).shape(), vec![3, 2]);
+
+        let e = a.expand_dims(-1);
+        assert_eq!(e.shape(), vec![2, 3, 1]);
+        assert_eq!(e.squeeze(&[]).shape(), vec![2, 3]);
+        assert_eq!(a.expand_dims(0).squeeze(&[0]).shape(), vec![2, 3]);
+        assert!(a.try_squeeze(&[0]).is_err());
+        assert!(a.try_expand_dims(3).is_err());
+
+        assert_eq!(e.flatten().shape(), vec![2, 3]);
+
+        let b = e.broadcast_to(&[2, 3, 2]);
+        assert_eq!(b.shape(), vec![2, 3, 2]);
+        assert_eq!(b.to_vec::<f32>()[..4], [1.0, 1.0, 2.0, 2.0]);
+        assert!(a.try_broadcast_to(&[4, 3]).is_err());
+
+        assert_eq!(a.tile(&[2, 1]).shape(), vec![4, 3]);
+        assert_eq!(a.tile(&[2]).shape(), vec![2, 6]);
+        let err = a.try_tile(&[]).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::InvalidParameter);
+        assert!(err.message().contains("reps"));
+    }
+}
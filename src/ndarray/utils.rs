@@ -0,0 +1,435 @@
+use super::{DType, NDArray};
+use crate::base::path_to_cstring;
+use crate::context::Context;
+use crate::error::Result;
+use crate::operator::{GetHandle, Operator, Shape};
+use either::Either;
+use mxnet_sys::*;
+use std::collections::HashMap;
+use std::ffi::{c_void, CStr, CString};
+use std::os::raw::c_char;
+use std::path::Path;
+use std::{ptr, result, slice};
+
+/// Save named NDArrays in the MXNet `.params` format, loadable as a dict in Python.
+pub fn save(path: impl AsRef<Path>, data: &[(&str, &NDArray)]) {
+    try_save(path, data).unwrap()
+}
+
+pub fn try_save(path: impl AsRef<Path>, data: &[(&str, &NDArray)]) -> Result<()> {
+    let fname = path_to_cstring(path.as_ref())?;
+    let keys = data
+        .iter()
+        .map(|(key, _)| CString::new(*key))
+        .collect::<result::Result<Vec<_>, _>>()?;
+    let mut key_ptrs = keys.iter().map(|key| key.as_ptr()).collect::<Vec<_>>();
+    let mut handles = data
+        .iter()
+        .map(|(_, array)| array.handle())
+        .collect::<Vec<_>>();
+
+    try_call!(MXNDArraySave(
+        fname.as_ptr(),
+        handles.len() as u32,
+        handles.as_mut_ptr(),
+        key_ptrs.as_mut_ptr()
+    ))
+}
+
+/// Save NDArrays without names, loadable as a list in Python.
+pub fn save_list(path: impl AsRef<Path>, data: &[&NDArray]) {
+    try_save_list(path, data).unwrap()
+}
+
+pub fn try_save_list(path: impl AsRef<Path>, data: &[&NDArray]) -> Result<()> {
+    let fname = path_to_cstring(path.as_ref())?;
+    let mut handles = data.iter().map(|array| array.handle()).collect::<Vec<_>>();
+
+    try_call!(MXNDArraySave(
+        fname.as_ptr(),
+        handles.len() as u32,
+        handles.as_mut_ptr(),
+        ptr::null_mut()
+    ))
+}
+
+/// Load NDArrays saved by `save`, `save_list` or Python's `mx.nd.save`.
+///
+/// Returns a list when the file was saved without names, otherwise a map.
+pub fn load(path: impl AsRef<Path>) -> Either<Vec<NDArray>, HashMap<String, NDArray>> {
+    try_load(path).unwrap()
+}
+
+pub fn try_load(path: impl AsRef<Path>) -> Result<Either<Vec<NDArray>, HashMap<String, NDArray>>> {
+    let fname = path_to_cstring(path.as_ref())?;
+    let mut out_size = 0;
+    let mut out_arr = ptr::null_mut();
+    let mut out_name_size = 0;
+    let mut out_names = ptr::null_mut();
+
+    try_call!(MXNDArrayLoad(
+        fname.as_ptr(),
+        &mut out_size,
+        &mut out_arr,
+        &mut out_name_size,
+        &mut out_names
+    ))?;
+
+    Ok(collect_loaded(out_size, out_arr, out_name_size, out_names))
+}
+
+/// Like `load`, but reads the `.params` content from memory.
+pub fn load_from_buffer(buf: &[u8]) -> Either<Vec<NDArray>, HashMap<String, NDArray>> {
+    try_load_from_buffer(buf).unwrap()
+}
+
+pub fn try_load_from_buffer(buf: &[u8]) -> Result<Either<Vec<NDArray>, HashMap<String, NDArray>>> {
+    let mut out_size = 0;
+    let mut out_arr = ptr::null_mut();
+    let mut out_name_size = 0;
+    let mut out_names = ptr::null_mut();
+
+    try_call!(MXNDArrayLoadFromBuffer(
+        buf.as_ptr() as *const c_void,
+        buf.len(),
+        &mut out_size,
+        &mut out_arr,
+        &mut out_name_size,
+        &mut out_names
+    ))?;
+
+    Ok(collect_loaded(out_size, out_arr, out_name_size, out_names))
+}
+
+/// Join arrays along an existing axis `dim`.
+pub fn concat(arrays: &[&NDArray], dim: i32) -> NDArray {
+    try_concat(arrays, dim).unwrap()
+}
+
+pub fn try_concat(arrays: &[&NDArray], dim: i32) -> Result<NDArray> {
+    Operator::try_new("concat")?
+        .try_push_inputs(arrays)?
+        .try_set_param("dim", &dim)?
+        .try_invoke()
+}
+
+/// Join arrays of the same shape along a new axis.
+pub fn stack(arrays: &[&NDArray], axis: i32) -> NDArray {
+    try_stack(arrays, axis).unwrap()
+}
+
+pub fn try_stack(arrays: &[&NDArray], axis: i32) -> Result<NDArray> {
+    Operator::try_new("stack")?
+        .try_push_inputs(arrays)?
+        .try_set_param("axis", &axis)?
+        .try_invoke()
+}
+
+/// Sum arrays of the same shape element-wise.
+pub fn add_n(arrays: &[&NDArray]) -> NDArray {
+    try_add_n(arrays).unwrap()
+}
+
+pub fn try_add_n(arrays: &[&NDArray]) -> Result<NDArray> {
+    Operator::try_new("add_n")?
+        .try_push_inputs(arrays)?
+        .try_invoke()
+}
+
+/// An uninitialized array.
+pub fn empty(shape: &[u32], ctx: Context, dtype: DType) -> NDArray {
+    try_empty(shape, ctx, dtype).unwrap()
+}
+
+pub fn try_empty(shape: &[u32], ctx: Context, dtype: DType) -> Result<NDArray> {
+    NDArray::builder()
+        .shape(shape)
+        .context(ctx)
+        .dtype(dtype)
+        .try_create()
+}
+
+pub fn zeros(shape: &[u32], ctx: Context, dtype: DType) -> NDArray {
+    try_zeros(shape, ctx, dtype).unwrap()
+}
+
+pub fn try_zeros(shape: &[u32], ctx: Context, dtype: DType) -> Result<NDArray> {
+    try_init("_zeros", shape, ctx, dtype)?.try_invoke()
+}
+
+pub fn ones(shape: &[u32], ctx: Context, dtype: DType) -> NDArray {
+    try_ones(shape, ctx, dtype).unwrap()
+}
+
+pub fn try_ones(shape: &[u32], ctx: Context, dtype: DType) -> Result<NDArray> {
+    try_init("_ones", shape, ctx, dtype)?.try_invoke()
+}
+
+/// An array filled with `value`.
+pub fn full(shape: &[u32], value: f64, ctx: Context, dtype: DType) -> NDArray {
+    try_full(shape, value, ctx, dtype).unwrap()
+}
+
+pub fn try_full(shape: &[u32], value: f64, ctx: Context, dtype: DType) -> Result<NDArray> {
+    try_init("_full", shape, ctx, dtype)?
+        .try_set_param("value", &value)?
+        .try_invoke()
+}
+
+fn try_init(op_name: &str, shape: &[u32], ctx: Context, dtype: DType) -> Result<Operator> {
+    let mut op = Operator::try_new(op_name)?;
+    op.try_set_param("shape", &Shape::from(shape))?
+        .try_set_param("ctx", &ctx)?
+        .try_set_param("dtype", &dtype)?;
+    Ok(op)
+}
+
+/// Evenly spaced values in `[start, stop)`.
+pub fn arange(start: f64, stop: f64, step: f64, ctx: Context, dtype: DType) -> NDArray {
+    try_arange(start, stop, step, ctx, dtype).unwrap()
+}
+
+pub fn try_arange(start: f64, stop: f64, step: f64, ctx: Context, dtype: DType) -> Result<NDArray> {
+    Operator::try_new("_arange")?
+        .try_set_param("start", &start)?
+        .try_set_param("stop", &stop)?
+        .try_set_param("step", &step)?
+        .try_set_param("ctx", &ctx)?
+        .try_set_param("dtype", &dtype)?
+        .try_invoke()
+}
+
+/// `num` evenly spaced values from `start` to `stop`, which is included
+/// if `endpoint` is set.
+pub fn linspace(
+    start: f64,
+    stop: f64,
+    num: u32,
+    endpoint: bool,
+    ctx: Context,
+    dtype: DType,
+) -> NDArray {
+    try_linspace(start, stop, num, endpoint, ctx, dtype).unwrap()
+}
+
+pub fn try_linspace(
+    start: f64,
+    stop: f64,
+    num: u32,
+    endpoint: bool,
+    ctx: Context,
+    dtype: DType,
+) -> Result<NDArray> {
+    Operator::try_new("_linspace")?
+        .try_set_param("start", &start)?
+        .try_set_param("stop", &stop)?
+        .try_set_param("num", &num)?
+        .try_set_param("endpoint", &endpoint)?
+        .try_set_param("ctx", &ctx)?
+        .try_set_param("dtype", &dtype)?
+        .try_invoke()
+}
+
+/// An `n` by `m` matrix with ones on the `k`-th diagonal. `m` of 0 means
+/// `n`, and a positive `k` is above the main diagonal.
+pub fn eye(n: u32, m: u32, k: i32, ctx: Context, dtype: DType) -> NDArray {
+    try_eye(n, m, k, ctx, dtype).unwrap()
+}
+
+pub fn try_eye(n: u32, m: u32, k: i32, ctx: Context, dtype: DType) -> Result<NDArray> {
+    Operator::try_new("_eye")?
+        .try_set_param("N", &n)?
+        .try_set_param("M", &m)?
+        .try_set_param("k", &k)?
+        .try_set_param("ctx", &ctx)?
+        .try_set_param("dtype", &dtype)?
+        .try_invoke()
+}
+
+/// Zeros with the shape, context and dtype of `array`.
+pub fn zeros_like(array: &NDArray) -> NDArray {
+    try_zeros_like(array).unwrap()
+}
+
+pub fn try_zeros_like(array: &NDArray) -> Result<NDArray> {
+    Operator::try_new("zeros_like")?
+        .push_input(array)
+        .try_invoke()
+}
+
+pub fn ones_like(array: &NDArray) -> NDArray {
+    try_ones_like(array).unwrap()
+}
+
+pub fn try_ones_like(array: &NDArray) -> Result<NDArray> {
+    Operator::try_new("ones_like")?
+        .push_input(array)
+        .try_invoke()
+}
+
+pub fn full_like(array: &NDArray, value: f64) -> NDArray {
+    try_full_like(array, value).unwrap()
+}
+
+pub fn try_full_like(array: &NDArray, value: f64) -> Result<NDArray> {
+    try_full(
+        &array.try_shape()?,
+        value,
+        array.try_context()?,
+        array.try_dtype()?,
+    )
+}
+
+/// Raw bytes
+impl NDArray {
+    /// Serialize a single NDArray, the inverse of `load_from_raw_bytes`.
+    pub fn save_raw_bytes(&self) -> Vec<u8> {
+        self.try_save_raw_bytes().unwrap()
+    }
+
+    pub fn try_save_raw_bytes(&self) -> Result<Vec<u8>> {
+        let mut out_size = 0;
+        let mut out_buf = ptr::null();
+        try_call!(MXNDArraySaveRawBytes(
+            self.handle(),
+            &mut out_size,
+            &mut out_buf
+        ))?;
+        Ok(unsafe { slice::from_raw_parts(out_buf as *const u8, out_size) }.to_vec())
+    }
+
+    pub fn load_from_raw_bytes(buf: &[u8]) -> NDArray {
+        NDArray::try_load_from_raw_bytes(buf).unwrap()
+    }
+
+    pub fn try_load_from_raw_bytes(buf: &[u8]) -> Result<NDArray> {
+        let mut handle = ptr::null_mut();
+        try_call!(MXNDArrayLoadFromRawBytes(
+            buf.as_ptr() as *const c_void,
+            buf.len(),
+            &mut handle
+        ))?;
+        Ok(NDArray::from(handle))
+    }
+}
+
+fn collect_loaded(
+    out_size: u32,
+    out_arr: *mut NDArrayHandle,
+    out_name_size: u32,
+    out_names: *mut *const c_char,
+) -> Either<Vec<NDArray>, HashMap<String, NDArray>> {
+    let arrays = unsafe { slice::from_raw_parts(out_arr, out_size as usize) }
+        .iter()
+        .map(|handle| NDArray::from(*handle));
+
+    if out_name_size == 0 {
+        Either::Left(arrays.collect())
+    } else {
+        let names = unsafe { slice::from_raw_parts(out_names, out_name_size as usize) }
+            .iter()
+            .map(|name| {
+                unsafe { CStr::from_ptr(*name) }
+                    .to_string_lossy()
+                    .into_owned()
+            });
+        Either::Right(names.zip(arrays).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn save_and_load() {
+        let a = NDArray::builder().data(&[1.0f32, 2.0]).create();
+        let b = NDArray::builder().data(&[3i32, 4, 5]).create();
+
+        // Unique per process, so concurrent test runs don't share the file.
+        let path = std::env::temp_dir().join(format!(
+            "mxnet_rs_save_and_load_{}.params",
+            std::process::id()
+        ));
+        save(&path, &[("a", &a), ("b", &b)]);
+        let loaded = load(&path).right().unwrap();
+        assert_eq!(loaded["a"].to_vec::<f32>(), vec![1.0, 2.0]);
+        assert_eq!(loaded["b"].to_vec::<i32>(), vec![3, 4, 5]);
+
+        save_list(&path, &[&a, &b]);
+        let buf = std::fs::read(&path).unwrap();
+        let loaded = load_from_buffer(&buf).left().unwrap();
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded[1].to_vec::<i32>(), vec![3, 4, 5]);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn concat_stack_add_n() {
+        let a = NDArray::builder()
+            .data(&[1.0f32, 2.0, 3.0, 4.0])
+            .shape(&[2, 2])
+            .create();
+        let b = NDArray::builder()
+            .data(&[5.0f32, 6.0, 7.0, 8.0])
+            .shape(&[2, 2])
+            .create();
+
+        let c = concat(&[&a, &b], 1);
+        assert_eq!(c.shape(), vec![2, 4]);
+        assert_eq!(
+            c.to_vec::<f32>(),
+            vec![1.0, 2.0, 5.0, 6.0, 3.0, 4.0, 7.0, 8.0]
+        );
+
+        let s = stack(&[&a, &b, &a], 0);
+        assert_eq!(s.shape(), vec![3, 2, 2]);
+
+        let sum = add_n(&[&a, &b, &a]);
+        assert_eq!(sum.to_vec::<f32>(), vec![7.0, 10.0, 13.0, 16.0]);
+
+        assert!(try_concat(&[&a, &s], 0).is_err());
+    }
+
+    #[test]
+    fn creation() {
+        let ctx = Context::default();
+        let a = zeros(&[2, 3], ctx, DType::F32);
+        assert_eq!(a.shape(), vec![2, 3]);
+        assert_eq!(a.to_vec::<f32>(), vec![0.0; 6]);
+
+        let a = ones(&[2], ctx, DType::I32);
+        assert_eq!(a.dtype(), DType::I32);
+        assert_eq!(a.to_vec::<i32>(), vec![1, 1]);
+
+        let a = full(&[2, 2], 1.5, ctx, DType::F64);
+        assert_eq!(a.to_vec::<f64>(), vec![1.5; 4]);
+        assert_eq!(empty(&[3, 1], ctx, DType::F32).shape(), vec![3, 1]);
+
+        let a = arange(0.0, 5.0, 2.0, ctx, DType::F32);
+        assert_eq!(a.to_vec::<f32>(), vec![0.0, 2.0, 4.0]);
+        let a = linspace(0.0, 1.0, 5, true, ctx, DType::F32);
+        assert_eq!(a.to_vec::<f32>(), vec![0.0, 0.25, 0.5, 0.75, 1.0]);
+        let a = eye(2, 3, 1, ctx, DType::F32);
+        assert_eq!(a.to_vec::<f32>(), vec![0.0, 1.0, 0.0, 0.0, 0.0, 1.0]);
+
+        let b = NDArray::builder().data(&[1i64, 2, 3]).create();
+        let z = zeros_like(&b);
+        assert_eq!(z.dtype(), DType::I64);
+        assert_eq!(z.context(), b.context());
+        assert_eq!(z.to_vec::<i64>(), vec![0, 0, 0]);
+        assert_eq!(ones_like(&b).to_vec::<i64>(), vec![1, 1, 1]);
+        assert_eq!(full_like(&b, 7.0).to_vec::<i64>(), vec![7, 7, 7]);
+    }
+
+    #[test]
+    fn raw_bytes() {
+        let a = NDArray::builder()
+            .data(&[1.0f32, 2.0, 3.0, 4.0])
+            .shape(&[2, 2])
+            .create();
+        let b = NDArray::load_from_raw_bytes(&a.save_raw_bytes());
+        assert_eq!(b.shape(), vec![2, 2]);
+        assert_eq!(b.to_vec::<f32>(), vec![1.0, 2.0, 3.0, 4.0]);
+    }
+}
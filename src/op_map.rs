@@ -1,95 +1,248 @@
+use crate::base::to_string_vec;
+use crate::error::{Error, ErrorKind, Result};
 use mxnet_sys::*;
 use std::collections::HashMap;
-use std::ffi::CStr;
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
 use std::ptr;
-use std::slice;
+use std::rc::Rc;
 
 thread_local! {
     pub static OP_MAP: OpMap = OpMap::new();
 }
 
-pub struct OpMap {
-    op_handles: HashMap<String, OpHandle>,
+/// An argument of an operator, either a tensor input or a param.
+#[derive(Debug, Clone)]
+pub struct ArgInfo {
+    pub name: String,
+    /// Declared type, like `Shape(tuple)`, `{'avg', 'max'}` or
+    /// `NDArray-or-Symbol` for inputs.
+    pub ty: String,
+    pub description: String,
+    /// Default of an optional param, as declared. None for required params
+    /// and inputs.
+    pub default: Option<String>,
 }
 
-impl OpMap {
-    pub fn new() -> OpMap {
-        let mut op_map = OpMap {
-            // symbol_creators: HashMap::new(),
-            op_handles: HashMap::new(),
+impl ArgInfo {
+    fn new(name: String, type_info: &str, description: String) -> ArgInfo {
+        // A type like `{'avg', 'max'},optional, default='max'`.
+        let end = if type_info.starts_with('{') {
+            type_info.find('}').map_or(type_info.len(), |i| i + 1)
+        } else {
+            type_info.find(',').unwrap_or(type_info.len())
         };
+        let default = type_info[end..]
+            .split("default=")
+            .nth(1)
+            .map(|value| value.trim().trim_matches('\'').to_owned());
+        ArgInfo {
+            name,
+            ty: type_info[..end].trim().to_owned(),
+            description,
+            default,
+        }
+    }
+
+    /// Whether `value` looks like a value of this param's type. Only
+    /// obviously wrong values are rejected, MXNet checks the rest.
+    pub fn accepts(&self, value: &str) -> bool {
+        let value = value.trim();
+        let ty = self.ty.trim_end_matches(" or None");
+        if value == "None" && (ty != self.ty || self.default.as_deref() == Some("None")) {
+            return true;
+        }
+        if ty.starts_with('{') {
+            return ty[1..ty.len() - 1]
+                .split(',')
+                .any(|name| name.trim().trim_matches('\'') == value);
+        }
+        let is_int = |value: &str| value.parse::<i64>().is_ok();
+        let is_float = |value: &str| value.parse::<f64>().is_ok();
+        match ty {
+            "boolean" => matches!(value, "0" | "1" | "true" | "false" | "True" | "False"),
+            "int" | "long" | "int64" => is_int(value),
+            "int (non-negative)" | "long (non-negative)" => value.parse::<u64>().is_ok(),
+            "float" | "double" | "real_t" => is_float(value),
+            // A single number is taken as a tuple of one.
+            _ if ty.starts_with("Shape") || ty.starts_with("tuple of") => {
+                let floats = ty.contains("<float>") || ty.contains("<double>");
+                value.starts_with('(')
+                    || value.starts_with('[')
+                    || is_int(value)
+                    || (floats && is_float(value))
+            }
+            _ => true,
+        }
+    }
+
+    /// Whether this is a tensor input rather than a param.
+    pub fn is_input(&self) -> bool {
+        self.ty.starts_with("NDArray") || self.ty.starts_with("Symbol")
+    }
+}
+
+/// Metadata of a registered operator.
+#[derive(Debug, Clone)]
+pub struct OpInfo {
+    pub name: String,
+    pub description: String,
+    pub args: Vec<ArgInfo>,
+    /// Param holding the number of inputs of variadic ops, e.g. `num_args`.
+    pub key_var_num_args: Option<String>,
+    pub return_type: Option<String>,
+    /// Other names the operator is registered under, e.g. `flatten` for
+    /// `Flatten`.
+    pub aliases: Vec<String>,
+    pub(crate) handle: OpHandle,
+}
+
+impl OpInfo {
+    fn new(handle: OpHandle) -> OpInfo {
+        let mut name = ptr::null();
+        let mut description = ptr::null();
+        let mut num_args = 0;
+        let mut arg_names = ptr::null_mut();
+        let mut arg_descriptions = ptr::null_mut();
+        let mut arg_type_infos = ptr::null_mut();
+        let mut key_var_num_args = ptr::null();
+        let mut return_type = ptr::null();
 
-        // let mut num_symbol_creators = 0;
-        // let mut symbol_creators = ptr::null_mut();
-        // check_call!(MXSymbolListAtomicSymbolCreators(
-        //     &mut num_symbol_creators,
-        //     &mut symbol_creators
-        // ));
-        // // let num_symbol_creators = num_symbol_creators as usize;
-        // let symbol_creators =
-        //     unsafe { slice::from_raw_parts(symbol_creators, num_symbol_creators as usize) };
-
-        // // for i in 0..num_symbol_creators {
-        // for symbol_creator in symbol_creators {
-        //     let mut name = ptr::null();
-        //     let mut description = ptr::null();
-        //     let mut num_args = 0;
-        //     let mut arg_names = ptr::null_mut();
-        //     let mut arg_descriptions = ptr::null_mut();
-        //     let mut arg_type_infos = ptr::null_mut();
-        //     let mut key_var_num_args = ptr::null();
-        //     let mut return_type = ptr::null();
-
-        //     check_call!(MXSymbolGetAtomicSymbolInfo(
-        //         // symbol_creators[i],
-        //         *symbol_creator,
-        //         &mut name,
-        //         &mut description,
-        //         &mut num_args,
-        //         &mut arg_names,
-        //         &mut arg_type_infos,
-        //         &mut arg_descriptions,
-        //         &mut key_var_num_args,
-        //         &mut return_type
-        //     ));
-
-        //     op_map.symbol_creators.insert(
-        //         unsafe { CStr::from_ptr(name) }.to_str().unwrap().to_owned(),
-        //         // symbol_creators[i],
-        //         *symbol_creator,
-        //     );
-        // }
+        check_call!(MXSymbolGetAtomicSymbolInfo(
+            handle,
+            &mut name,
+            &mut description,
+            &mut num_args,
+            &mut arg_names,
+            &mut arg_type_infos,
+            &mut arg_descriptions,
+            &mut key_var_num_args,
+            &mut return_type
+        ));
 
+        let arg_names = to_string_vec(num_args, arg_names);
+        let arg_type_infos = to_string_vec(num_args, arg_type_infos);
+        let arg_descriptions = to_string_vec(num_args, arg_descriptions);
+        let args = arg_names
+            .into_iter()
+            .zip(arg_type_infos)
+            .zip(arg_descriptions)
+            .map(|((name, type_info), description)| ArgInfo::new(name, &type_info, description))
+            .collect();
+
+        OpInfo {
+            name: optional_string(name).unwrap_or_default(),
+            description: optional_string(description).unwrap_or_default(),
+            args,
+            key_var_num_args: optional_string(key_var_num_args),
+            return_type: optional_string(return_type),
+            aliases: Vec::new(),
+            handle,
+        }
+    }
+
+    pub fn arg(&self, name: &str) -> Option<&ArgInfo> {
+        self.args.iter().find(|arg| arg.name == name)
+    }
+
+    /// The params, i.e. the arguments that aren't tensor inputs.
+    pub fn params(&self) -> impl Iterator<Item = &ArgInfo> {
+        self.args.iter().filter(|arg| !arg.is_input())
+    }
+}
+
+/// Registered operators by name, aliases included. Their info is queried
+/// once, when the map is created.
+pub struct OpMap {
+    ops: HashMap<String, Rc<OpInfo>>,
+}
+
+impl OpMap {
+    pub fn new() -> OpMap {
         let mut num_ops = 0;
         let mut op_names = ptr::null_mut();
         check_call!(NNListAllOpNames(&mut num_ops, &mut op_names));
-        let op_names = unsafe { slice::from_raw_parts(op_names, num_ops as usize) };
+        let op_names = to_string_vec(num_ops, op_names);
+
+        // Aliases share the handle of their operator.
+        let mut by_handle: HashMap<OpHandle, (OpInfo, Vec<String>)> = HashMap::new();
         for op_name in op_names {
+            let c_name = CString::new(op_name.as_str()).unwrap();
             let mut handle = ptr::null_mut();
-            check_call!(NNGetOpHandle(*op_name, &mut handle));
-            op_map.op_handles.insert(
-                unsafe { CStr::from_ptr(*op_name) }
-                    .to_string_lossy()
-                    .into_owned(),
-                handle,
-            );
+            check_call!(NNGetOpHandle(c_name.as_ptr(), &mut handle));
+            by_handle
+                .entry(handle)
+                .or_insert_with(|| (OpInfo::new(handle), Vec::new()))
+                .1
+                .push(op_name);
         }
 
-        op_map
+        let mut ops = HashMap::new();
+        for (_, (mut info, mut names)) in by_handle {
+            names.sort();
+            info.aliases = names
+                .iter()
+                .filter(|name| **name != info.name)
+                .cloned()
+                .collect();
+            let info = Rc::new(info);
+            for name in names {
+                ops.insert(name, info.clone());
+            }
+        }
+
+        OpMap { ops }
+    }
+
+    /// Every registered name, aliases included, sorted.
+    pub fn list_ops(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.ops.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        names
     }
 
-    // pub fn get_symbol_creator(&self, name: &str) -> AtomicSymbolCreator {
-    //     *self
-    //         .symbol_creators
-    //         .get(name)
-    //         .unwrap_or(&self.get_op_handle(name))
-    // }
+    /// Registered names starting with `prefix`, sorted.
+    pub fn search(&self, prefix: &str) -> Vec<&str> {
+        let mut names = self.list_ops();
+        names.retain(|name| name.starts_with(prefix));
+        names
+    }
+
+    pub fn op_info(&self, name: &str) -> Rc<OpInfo> {
+        self.try_op_info(name).unwrap()
+    }
+
+    pub fn try_op_info(&self, name: &str) -> Result<Rc<OpInfo>> {
+        self.ops.get(name).cloned().ok_or_else(|| {
+            Error::new(
+                ErrorKind::UnknownOperator,
+                format!("Cannot find operator {}", name),
+            )
+        })
+    }
 
     pub fn get_op_handle(&self, name: &str) -> OpHandle {
-        self.op_handles[name]
+        self.try_get_op_handle(name).unwrap()
+    }
+
+    pub fn try_get_op_handle(&self, name: &str) -> Result<OpHandle> {
+        self.try_op_info(name).map(|info| info.handle)
+    }
+}
+
+impl Default for OpMap {
+    fn default() -> OpMap {
+        OpMap::new()
     }
 }
 
+fn optional_string(s: *const c_char) -> Option<String> {
+    if s.is_null() {
+        return None;
+    }
+    Some(unsafe { CStr::from_ptr(s) }.to_string_lossy().into_owned()).filter(|s| !s.is_empty())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -98,6 +251,54 @@ mod tests {
     fn create_op_map() {
         OP_MAP.with(|op_map| {
             let _add = op_map.get_op_handle("_plus");
+            let err = op_map.try_get_op_handle("_no_such_op").unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::UnknownOperator);
         })
     }
+
+    #[test]
+    fn op_info() {
+        OP_MAP.with(|op_map| {
+            let info = op_map.op_info("Convolution");
+            assert_eq!(info.name, "Convolution");
+            assert!(!info.description.is_empty());
+            assert!(info.arg("data").unwrap().is_input());
+            let kernel = info.arg("kernel").unwrap();
+            assert_eq!(kernel.ty, "Shape(tuple)");
+            assert_eq!(kernel.default, None);
+            assert_eq!(info.arg("no_bias").unwrap().default.as_deref(), Some("0"));
+            assert!(info.params().all(|arg| !arg.is_input()));
+
+            let flatten = op_map.op_info("flatten");
+            assert_eq!(flatten.name, "Flatten");
+            assert!(flatten.aliases.contains(&"flatten".to_owned()));
+
+            let concat = op_map.op_info("concat");
+            assert_eq!(concat.key_var_num_args.as_deref(), Some("num_args"));
+
+            assert!(op_map.list_ops().contains(&"_plus"));
+            let linalg = op_map.search("_linalg_");
+            assert!(linalg.contains(&"_linalg_gemm2"));
+            assert!(linalg.iter().all(|name| name.starts_with("_linalg_")));
+            assert!(op_map.try_op_info("_no_such_op").is_err());
+        })
+    }
+
+    #[test]
+    fn accepts() {
+        let sizes = ArgInfo::new(
+            "sizes".to_owned(),
+            "tuple of <float>, optional, default=[1]",
+            String::new(),
+        );
+        assert_eq!(sizes.ty, "tuple of <float>");
+        assert!(sizes.accepts("0.5"));
+        assert!(sizes.accepts("2"));
+        assert!(sizes.accepts("(0.5, 0.25)"));
+        assert!(!sizes.accepts("large"));
+
+        let kernel = ArgInfo::new("kernel".to_owned(), "Shape(tuple), required", String::new());
+        assert!(kernel.accepts("3"));
+        assert!(!kernel.accepts("1.5"));
+    }
 }
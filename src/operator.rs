@@ -1,5 +1,6 @@
+use super::error::{Error, ErrorKind, Result};
 use super::ndarray::NDArray;
-use super::op_map::OP_MAP;
+use super::op_map::{OpInfo, OP_MAP};
 use super::symbol::Symbol;
 // use mxnet_sys::{
 //     AtomicSymbolCreator, MXImperativeInvoke, MXSymbolCompose, MXSymbolCreateAtomicSymbol,
@@ -7,93 +8,147 @@ use super::symbol::Symbol;
 // };
 use mxnet_sys::*;
 use std::collections::HashMap;
-use std::ffi::{c_void, CStr, CString};
+use std::convert::TryInto;
+use std::ffi::{c_void, CString};
+use std::fmt;
 use std::ptr;
+use std::rc::Rc;
 use std::slice;
 
 pub trait GetHandle {
     fn handle(&self) -> *mut c_void;
 }
 
-#[derive(Debug)]
+impl<T: GetHandle> GetHandle for &T {
+    fn handle(&self) -> *mut c_void {
+        (**self).handle()
+    }
+}
+
+/// A tuple-valued operator parameter, formatted the way MXNet parses it,
+/// e.g. `(3, 3)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tuple<T>(pub Vec<T>);
+
+/// Parameters declared as `Shape(tuple)`. Dimensions are signed because
+/// `reshape` style parameters use negative special values.
+pub type Shape = Tuple<i64>;
+
+impl<T: fmt::Display> fmt::Display for Tuple<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "(")?;
+        for (i, value) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", value)?;
+        }
+        // A single element needs the trailing comma, as in Python.
+        if self.0.len() == 1 {
+            write!(f, ",")?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl<T> From<Vec<T>> for Tuple<T> {
+    fn from(values: Vec<T>) -> Tuple<T> {
+        Tuple(values)
+    }
+}
+
+impl<'a, T: Clone> From<&'a [T]> for Tuple<T> {
+    fn from(values: &'a [T]) -> Tuple<T> {
+        Tuple(values.to_vec())
+    }
+}
+
+impl<T, const N: usize> From<[T; N]> for Tuple<T> {
+    fn from(values: [T; N]) -> Tuple<T> {
+        Tuple(Vec::from(values))
+    }
+}
+
+/// `NDArray::shape` returns `u32` dimensions.
+impl<'a> From<&'a [u32]> for Shape {
+    fn from(shape: &'a [u32]) -> Shape {
+        Tuple(shape.iter().map(|&dim| i64::from(dim)).collect())
+    }
+}
+
+macro_rules! tuple_from {
+    ($($value:ident: $t:ident),*) => {
+        impl<T> From<($($t,)*)> for Tuple<T> {
+            fn from(($($value,)*): ($($t,)*)) -> Tuple<T> {
+                Tuple(vec![$($value),*])
+            }
+        }
+    };
+}
+
+tuple_from!(a: T);
+tuple_from!(a: T, b: T);
+tuple_from!(a: T, b: T, c: T);
+tuple_from!(a: T, b: T, c: T, d: T);
+tuple_from!(a: T, b: T, c: T, d: T, e: T);
+tuple_from!(a: T, b: T, c: T, d: T, e: T, f: T);
+
+#[derive(Debug, Clone)]
 pub struct Operator {
     // params_desc: HashMap<String, String>,
     // variable_params: bool,
 
     // Params has to store CString, or a memory error arise.
     params: HashMap<CString, CString>,
-    // Param index
+    // Position of the next `push_param`, among the params only.
     index: usize,
     // input_symbols: Vec<SymbolHandle>,
     // input_ndarrays: Vec<NDArrayHandle>,
     inputs: Vec<*mut c_void>,
     input_keys: Vec<CString>,
-    arg_names: Vec<CString>,
-    handle: AtomicSymbolCreator,
+    info: Rc<OpInfo>,
 }
 
 impl Operator {
     pub fn new(operator_name: &str) -> Operator {
-        OP_MAP.with(|op_map| {
-            let handle = op_map.get_op_handle(operator_name);
-
-            // I have no idea why this piece of code is repeated
-            let mut name = ptr::null();
-            let mut description = ptr::null();
-            let mut num_args = 0;
-            let mut arg_names = ptr::null_mut();
-            let mut arg_descriptions = ptr::null_mut();
-            let mut arg_type_infos = ptr::null_mut();
-            let mut key_var_num_args = ptr::null();
-            let mut return_type = ptr::null();
-
-            check_call!(MXSymbolGetAtomicSymbolInfo(
-                // symbol_creators[i],
-                handle,
-                &mut name,
-                &mut description,
-                &mut num_args,
-                &mut arg_names,
-                &mut arg_type_infos,
-                &mut arg_descriptions,
-                &mut key_var_num_args,
-                &mut return_type
-            ));
+        Operator::try_new(operator_name).unwrap()
+    }
 
-            let arg_names = unsafe { slice::from_raw_parts(arg_names, num_args as usize) }
-                .iter()
-                .map(|name| unsafe { CStr::from_ptr(*name).to_owned() })
-                .collect();
-
-            Operator {
-                // params_desc: HashMap::new(),
-                // variable_params: false,
-                params: HashMap::new(),
-                index: 0,
-                // input_symbols: Vec::new(),
-                // input_ndarrays: Vec::new(),
-                inputs: Vec::new(),
-                input_keys: Vec::new(),
-                arg_names,
-                handle,
-            }
+    pub fn try_new(operator_name: &str) -> Result<Operator> {
+        let info = OP_MAP.with(|op_map| op_map.try_op_info(operator_name))?;
+        Ok(Operator {
+            // params_desc: HashMap::new(),
+            // variable_params: false,
+            params: HashMap::new(),
+            index: 0,
+            // input_symbols: Vec::new(),
+            // input_ndarrays: Vec::new(),
+            inputs: Vec::new(),
+            input_keys: Vec::new(),
+            info,
         })
     }
 
+    /// Metadata of the operator, shared with `OP_MAP`.
+    pub fn info(&self) -> &OpInfo {
+        &self.info
+    }
+
     // pub fn set_input()
 
     pub fn create_symbol(&mut self, name: Option<&str>) -> Symbol {
+        self.try_create_symbol(name).unwrap()
+    }
+
+    pub fn try_create_symbol(&mut self, name: Option<&str>) -> Result<Symbol> {
         if self.input_keys.len() > 0 {
             assert_eq!(self.input_keys.len(), self.inputs.len());
         }
 
-        // let pname = if name.is_empty() {
-        //     ptr::null()
-        // } else {
-        //     CString::new(name).unwrap().as_ptr()
-        // };
-        let pname = if let Some(name) = name {
-            CString::new(name).unwrap().as_ptr()
+        // Keep the CString alive until MXSymbolCompose returns.
+        let name = name.map(CString::new).transpose()?;
+        let pname = if let Some(name) = &name {
+            name.as_ptr()
         } else {
             ptr::null()
         };
@@ -118,31 +173,71 @@ impl Operator {
             ptr::null_mut()
         };
 
-        check_call!(MXSymbolCreateAtomicSymbol(
-            self.handle,
+        try_call!(MXSymbolCreateAtomicSymbol(
+            self.info.handle,
             param_keys.len() as u32,
             param_keys.as_mut_ptr(),
             param_values.as_mut_ptr(),
             &mut symbol_handle
-        ));
+        ))?;
 
-        check_call!(MXSymbolCompose(
+        // Take ownership first so the handle is freed if composing fails.
+        let symbol = Symbol::from(symbol_handle);
+
+        try_call!(MXSymbolCompose(
             symbol_handle,
             pname,
             self.inputs.len() as u32,
             input_keys_p,
             self.inputs.as_mut_ptr()
-        ));
+        ))?;
+
+        Ok(symbol)
+    }
 
-        Symbol::from(symbol_handle)
+    /// Create the symbol of an op with `N` outputs, like `_linalg_gelqf`,
+    /// and split it into one symbol per output.
+    pub fn create_symbol_n<const N: usize>(&mut self, name: Option<&str>) -> [Symbol; N] {
+        self.try_create_symbol_n(name).unwrap()
+    }
+
+    pub fn try_create_symbol_n<const N: usize>(
+        &mut self,
+        name: Option<&str>,
+    ) -> Result<[Symbol; N]> {
+        into_array(self.try_create_symbol_many(name)?)
+    }
+
+    /// Create the symbol and split it into one symbol per output, for ops
+    /// like `split` where the count depends on the params.
+    pub fn create_symbol_many(&mut self, name: Option<&str>) -> Vec<Symbol> {
+        self.try_create_symbol_many(name).unwrap()
+    }
+
+    pub fn try_create_symbol_many(&mut self, name: Option<&str>) -> Result<Vec<Symbol>> {
+        let symbol = self.try_create_symbol(name)?;
+        (0..symbol.try_num_outputs()?)
+            .map(|index| symbol.try_get_output(index))
+            .collect()
     }
 
     pub fn invoke_with(&mut self, output: &mut NDArray) {
+        self.try_invoke_with(output).unwrap()
+    }
+
+    pub fn try_invoke_with(&mut self, output: &mut NDArray) -> Result<()> {
         let mut output_handles = vec![output.handle()];
-        self.invoke_with_handles(&mut output_handles);
+        self.try_invoke_with_handles(&mut output_handles)
     }
 
     pub fn invoke_with_handles(&mut self, output_handles: &mut Vec<NDArrayHandle>) {
+        self.try_invoke_with_handles(output_handles).unwrap()
+    }
+
+    pub fn try_invoke_with_handles(
+        &mut self,
+        output_handles: &mut Vec<NDArrayHandle>,
+    ) -> Result<()> {
         if self.input_keys.len() > 0 {
             assert_eq!(self.input_keys.len(), self.inputs.len());
         }
@@ -169,8 +264,8 @@ impl Operator {
 
         // println!("Before call");
 
-        check_call!(MXImperativeInvoke(
-            self.handle,
+        try_call!(MXImperativeInvoke(
+            self.info.handle,
             num_inputs,
             self.inputs.as_mut_ptr(),
             &mut num_outputs,
@@ -178,74 +273,182 @@ impl Operator {
             param_keys.len() as i32,
             param_keys.as_mut_ptr(),
             param_values.as_mut_ptr(),
-        ));
+        ))?;
 
         // println!("After call");
 
         if output_handles.len() > 0 {
             // println!("return if has output handles");
-            return;
+            return Ok(());
         }
 
         let handles = unsafe { slice::from_raw_parts(outputs_receiver, num_outputs as usize) };
         for handle in handles {
             output_handles.push(*handle);
         }
+
+        Ok(())
     }
 
     pub fn push_input(&mut self, value: &impl GetHandle) -> &mut Self {
         self.inputs.push(value.handle());
-        self.index += 1;
         self
     }
 
+    /// Push the inputs of a variadic op like `concat` or `add_n`, and set its
+    /// var-num-args param (`num_args`) to the number of inputs.
+    pub fn push_inputs(&mut self, values: &[impl GetHandle]) -> &mut Self {
+        self.try_push_inputs(values).unwrap()
+    }
+
+    pub fn try_push_inputs(&mut self, values: &[impl GetHandle]) -> Result<&mut Self> {
+        for value in values {
+            self.push_input(value);
+        }
+        if let Some(key) = self.info.key_var_num_args.clone() {
+            let num_args = self.inputs.len();
+            self.try_set_param(&key, &num_args)?;
+        }
+        Ok(self)
+    }
+
     pub fn set_input(&mut self, name: &str, value: &impl GetHandle) -> &mut Self {
         self.input_keys.push(CString::new(name).unwrap());
         self.inputs.push(value.handle());
-        self.index += 1;
         self
     }
 
     // Currently no function return NDArray[]
     pub fn invoke_many(&mut self) -> Vec<NDArray> {
+        self.try_invoke_many().unwrap()
+    }
+
+    pub fn try_invoke_many(&mut self) -> Result<Vec<NDArray>> {
         let mut output_handles = Vec::new();
-        self.invoke_with_handles(&mut output_handles);
+        self.try_invoke_with_handles(&mut output_handles)?;
         let mut outputs = Vec::new();
         for handle in &output_handles {
             outputs.push(NDArray::from(*handle));
         }
-        outputs
+        Ok(outputs)
+    }
+
+    /// Invoke an op with `N` outputs, like `_linalg_gelqf`.
+    pub fn invoke_n<const N: usize>(&mut self) -> [NDArray; N] {
+        self.try_invoke_n().unwrap()
+    }
+
+    pub fn try_invoke_n<const N: usize>(&mut self) -> Result<[NDArray; N]> {
+        into_array(self.try_invoke_many()?)
     }
 
     pub fn invoke(&mut self) -> NDArray {
-        let mut ret = NDArray::new();
-        self.invoke_with(&mut ret);
-        ret
+        self.try_invoke().unwrap()
     }
 
+    pub fn try_invoke(&mut self) -> Result<NDArray> {
+        let mut ret = NDArray::try_new()?;
+        self.try_invoke_with(&mut ret)?;
+        Ok(ret)
+    }
+
+    /// Set a param as is, MXNet reports a bad one when the op is run. Use
+    /// `try_set_param` to check it first.
     pub fn set_param(&mut self, name: &str, value: &impl ToString) -> &mut Self {
-        let value_str = value.to_string();
-        // println!("{}:{}", name, &value_str);
         self.params.insert(
             CString::new(name).unwrap(),
-            CString::new(value_str).unwrap(),
+            CString::new(value.to_string()).unwrap(),
         );
         self
     }
 
-    // It seems never used.
+    /// Set a param, checking that the op declares it and that the value
+    /// looks like its type. Attributes like `__lr_mult__` and params of ops
+    /// that declare none are passed through as is.
+    pub fn try_set_param(&mut self, name: &str, value: &impl ToString) -> Result<&mut Self> {
+        let value = value.to_string();
+        self.check_param(name, &value)?;
+        self.params
+            .insert(CString::new(name)?, CString::new(value)?);
+        Ok(self)
+    }
+
+    /// Set the `pos`-th param, not counting the tensor inputs.
     pub fn set_param_at(&mut self, pos: usize, value: &impl ToString) -> &mut Self {
-        let value_str = value.to_string();
-        self.params.insert(
-            self.arg_names[pos].clone(),
-            CString::new(value_str).unwrap(),
-        );
-        self
+        self.try_set_param_at(pos, value).unwrap()
     }
 
+    pub fn try_set_param_at(&mut self, pos: usize, value: &impl ToString) -> Result<&mut Self> {
+        let name = match self.info.params().nth(pos) {
+            Some(arg) => arg.name.clone(),
+            None => {
+                return Err(Error::new(
+                    ErrorKind::InvalidParameter,
+                    format!(
+                        "Operator {} has no param at {}, params: {}",
+                        self.info.name,
+                        pos,
+                        self.param_names()
+                    ),
+                ))
+            }
+        };
+        self.try_set_param(&name, value)
+    }
+
+    /// Set the next param, in declared order.
     pub fn push_param(&mut self, value: &impl ToString) -> &mut Self {
-        self.set_param_at(self.index, value)
+        self.try_push_param(value).unwrap()
+    }
+
+    pub fn try_push_param(&mut self, value: &impl ToString) -> Result<&mut Self> {
+        self.try_set_param_at(self.index, value)?;
+        self.index += 1;
+        Ok(self)
     }
+
+    fn check_param(&self, name: &str, value: &str) -> Result<()> {
+        let info = &self.info;
+        if name.starts_with("__") || info.params().next().is_none() {
+            return Ok(());
+        }
+        let arg = info.params().find(|arg| arg.name == name).ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidParameter,
+                format!(
+                    "Cannot find param '{}' of operator {}, valid params: {}",
+                    name,
+                    info.name,
+                    self.param_names()
+                ),
+            )
+        })?;
+        if !arg.accepts(value) {
+            return Err(Error::new(
+                ErrorKind::InvalidParameter,
+                format!(
+                    "Invalid value '{}' for param '{}' of operator {}, expected {}",
+                    value, name, info.name, arg.ty
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    fn param_names(&self) -> String {
+        let names: Vec<&str> = self.info.params().map(|arg| arg.name.as_str()).collect();
+        names.join(", ")
+    }
+}
+
+fn into_array<T, const N: usize>(outputs: Vec<T>) -> Result<[T; N]> {
+    let count = outputs.len();
+    outputs.try_into().map_err(|_| {
+        Error::new(
+            ErrorKind::Other,
+            format!("expected {} outputs, got {}", N, count),
+        )
+    })
 }
 
 #[cfg(test)]
@@ -272,11 +475,120 @@ mod tests {
 
         let _ = Operator::new("_plus_scalar")
             .push_input(&a1)
-            .set_param_at(1, &0.1)
+            .set_param_at(0, &0.1)
             .invoke_with(&mut a3);
         // println!("{:?}", op);
         // println!("{:?}", op.params);
 
         println!("{:?}", a3.size());
     }
+
+    #[test]
+    fn invoke_shape_mismatch() {
+        use crate::error::ErrorKind;
+
+        let a1 = ndarray::NDArrayBuilder::new().data(&[1.0, 2.0]).create();
+        let a2 = ndarray::NDArrayBuilder::new()
+            .data(&[1.0, 2.0, 3.0])
+            .create();
+        let err = Operator::new("_plus")
+            .push_input(&a1)
+            .push_input(&a2)
+            .try_invoke()
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), ErrorKind::ShapeMismatch);
+        assert_eq!(err.call(), Some("MXImperativeInvoke"));
+
+        assert!(Operator::try_new("_no_such_op").is_err());
+    }
+
+    #[test]
+    fn invalid_param() {
+        use crate::error::ErrorKind;
+
+        let mut op = Operator::new("Convolution");
+        let err = op
+            .try_set_param("kernal", &Shape::from((3, 3)))
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), ErrorKind::InvalidParameter);
+        assert!(err.message().contains("kernel"));
+        assert!(err.message().contains("num_filter"));
+
+        assert!(op.try_set_param("num_filter", &"many").is_err());
+        assert!(op.try_set_param("num_filter", &-1).is_err());
+        assert!(op.try_set_param("kernel", &1.5).is_err());
+        assert!(op.try_set_param("no_bias", &"maybe").is_err());
+        assert!(op.try_set_param("layout", &"NCWH").is_err());
+
+        assert!(op.try_set_param("num_filter", &64).is_ok());
+        assert!(op.try_set_param("kernel", &Shape::from((3, 3))).is_ok());
+        assert!(op.try_set_param("no_bias", &true).is_ok());
+        assert!(op.try_set_param("layout", &"NCHW").is_ok());
+        assert!(op.try_set_param("cudnn_tune", &"None").is_ok());
+        assert!(op.try_set_param("__lr_mult__", &0.1).is_ok());
+
+        // The first param, the input `data` isn't counted.
+        let a = ndarray::NDArrayBuilder::new().data(&[1.0f32]).create();
+        let out = Operator::new("_plus_scalar")
+            .push_input(&a)
+            .push_param(&2.0)
+            .invoke();
+        assert_eq!(out.to_vec::<f32>(), vec![3.0]);
+        assert!(Operator::new("_plus_scalar")
+            .try_set_param_at(9, &2.0)
+            .is_err());
+    }
+
+    #[test]
+    fn push_inputs() {
+        let a1 = ndarray::NDArrayBuilder::new().data(&[1.0f32, 2.0]).create();
+        let a2 = ndarray::NDArrayBuilder::new().data(&[3.0f32]).create();
+        let out = Operator::new("concat")
+            .push_inputs(&[&a1, &a2])
+            .set_param("dim", &0)
+            .invoke();
+        assert_eq!(out.to_vec::<f32>(), vec![1.0, 2.0, 3.0]);
+
+        let out = Operator::new("add_n")
+            .push_inputs(&[a1.clone(), a1])
+            .invoke();
+        assert_eq!(out.to_vec::<f32>(), vec![2.0, 4.0]);
+    }
+
+    #[test]
+    fn invoke_n() {
+        let a = ndarray::NDArrayBuilder::new()
+            .data(&[1.0f32, 2.0, 3.0, 4.0])
+            .shape(&[2, 2])
+            .create();
+        let [mean, var] = Operator::new("moments")
+            .push_input(&a)
+            .set_param("axes", &Shape::from((1,)))
+            .invoke_n();
+        assert_eq!(mean.to_vec::<f32>(), vec![1.5, 3.5]);
+        assert_eq!(var.to_vec::<f32>(), vec![0.25, 0.25]);
+
+        assert!(Operator::new("moments")
+            .push_input(&a)
+            .try_invoke_n::<3>()
+            .is_err());
+
+        let data = symbol::Symbol::new("data");
+        let [q, l] = Operator::new("_linalg_gelqf")
+            .push_input(&data)
+            .create_symbol_n(Some("lq"));
+        assert_eq!(q.list_outputs().len(), 1);
+        assert_ne!(q.list_outputs(), l.list_outputs());
+    }
+
+    #[test]
+    fn tuple_param() {
+        assert_eq!(Shape::from((3, 3)).to_string(), "(3, 3)");
+        assert_eq!(Shape::from(vec![2]).to_string(), "(2,)");
+        assert_eq!(Shape::from(&[1u32, 2, 3][..]).to_string(), "(1, 2, 3)");
+        assert_eq!(Tuple::from([0.5f32, 1.0]).to_string(), "(0.5, 1)");
+        assert_eq!(Tuple::<f32>::from(vec![]).to_string(), "()");
+    }
 }
@@ -1,35 +1,131 @@
+use crate::base::{path_to_cstring, to_string_vec};
+use crate::error::{Error, ErrorKind, Result};
+use crate::ndarray::DType;
 use crate::operator::{GetHandle, Operator};
-use mxnet_sys::{MXSymbolCreateVariable, MXSymbolFree, SymbolHandle};
+use mxnet_sys::*;
+use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::path::Path;
 use std::ptr;
 use std::rc::Rc;
+use std::{result, slice};
 
+/// Shapes of arguments, outputs and auxiliary states, in the order of
+/// `list_arguments`, `list_outputs` and `list_auxiliary_states`.
+pub type Shapes = (Vec<Vec<u32>>, Vec<Vec<u32>>, Vec<Vec<u32>>);
+
+/// Types of arguments, outputs and auxiliary states.
+pub type Types = (Vec<DType>, Vec<DType>, Vec<DType>);
+
+// Implement add, sub, mul, div, mod for Symbol and f32, owned or borrowed,
+// and the `broadcast_*` methods for inputs of different shapes. Unlike for
+// NDArray, the operators can't pick the broadcast op themselves, shapes are
+// only known once the symbol is bound.
 macro_rules! ops {
     (
         $op_name:expr,
+        $broadcast_name:ident, $try_broadcast_name:ident,
         $op_class:ident::$op_method:ident
     ) => {
-        impl std::ops::$op_class for Symbol {
+        impl Symbol {
+            /// Like the operator, but broadcasting axes of size 1 so the
+            /// inputs may have different shapes.
+            pub fn $broadcast_name(&self, rhs: &Symbol) -> Symbol {
+                self.$try_broadcast_name(rhs).unwrap()
+            }
+
+            pub fn $try_broadcast_name(&self, rhs: &Symbol) -> Result<Symbol> {
+                Operator::try_new(stringify!($broadcast_name))?
+                    .push_input(self)
+                    .push_input(rhs)
+                    .try_create_symbol(None)
+            }
+        }
+
+        /// Elementwise, the inputs must have the same shape when bound. See
+        /// the `broadcast_*` methods for inputs that need broadcasting.
+        impl<'a, 'b> std::ops::$op_class<&'b Symbol> for &'a Symbol {
             type Output = Symbol;
 
-            fn $op_method(self, rhs: Symbol) -> Symbol {
+            fn $op_method(self, rhs: &'b Symbol) -> Symbol {
                 Operator::new($op_name)
-                    .push_input(&self)
-                    .push_input(&rhs)
+                    .push_input(self)
+                    .push_input(rhs)
                     .create_symbol(None)
             }
         }
 
-        impl std::ops::$op_class<f32> for Symbol {
+        impl std::ops::$op_class for Symbol {
+            type Output = Symbol;
+
+            fn $op_method(self, rhs: Symbol) -> Symbol {
+                (&self).$op_method(&rhs)
+            }
+        }
+
+        impl<'a> std::ops::$op_class<&'a Symbol> for Symbol {
+            type Output = Symbol;
+
+            fn $op_method(self, rhs: &'a Symbol) -> Symbol {
+                (&self).$op_method(rhs)
+            }
+        }
+
+        impl<'a> std::ops::$op_class<Symbol> for &'a Symbol {
+            type Output = Symbol;
+
+            fn $op_method(self, rhs: Symbol) -> Symbol {
+                self.$op_method(&rhs)
+            }
+        }
+
+        impl<'a> std::ops::$op_class<f32> for &'a Symbol {
             type Output = Symbol;
 
             fn $op_method(self, rhs: f32) -> Symbol {
                 Operator::new(concat!($op_name, "Scalar"))
-                    .push_input(&self)
+                    .push_input(self)
                     .set_param("scalar", &rhs)
                     .create_symbol(None)
             }
         }
+
+        impl std::ops::$op_class<f32> for Symbol {
+            type Output = Symbol;
+
+            fn $op_method(self, rhs: f32) -> Symbol {
+                (&self).$op_method(rhs)
+            }
+        }
+    };
+}
+
+// Implement `f32 op Symbol`.
+macro_rules! rscalar_ops {
+    (
+        $op_name:expr,
+        $op_class:ident::$op_method:ident
+    ) => {
+        impl<'a> std::ops::$op_class<&'a Symbol> for f32 {
+            type Output = Symbol;
+
+            fn $op_method(self, rhs: &'a Symbol) -> Symbol {
+                Operator::new($op_name)
+                    .push_input(rhs)
+                    .set_param("scalar", &self)
+                    .create_symbol(None)
+            }
+        }
+
+        impl std::ops::$op_class<Symbol> for f32 {
+            type Output = Symbol;
+
+            fn $op_method(self, rhs: Symbol) -> Symbol {
+                self.$op_method(&rhs)
+            }
+        }
     };
 }
 
@@ -53,25 +149,481 @@ impl Drop for SymBlob {
     }
 }
 
+/// A node of a symbolic graph.
+///
+/// `+`, `-`, `*`, `/` and `%` work on owned or borrowed symbols and `f32`
+/// scalars, so `&a * &b + &c` composes without cloning. They are
+/// elementwise: inputs must have the same shape at bind time, use
+/// `broadcast_add` and friends otherwise.
+#[derive(Clone)]
 pub struct Symbol {
     blob: Rc<SymBlob>,
 }
 
-ops!("_Plus", Add::add);
-ops!("_Minus", Sub::sub);
-ops!("_Mul", Mul::mul);
-ops!("_Div", Div::div);
-ops!("_Mod", Rem::rem);
+ops!("_Plus", broadcast_add, try_broadcast_add, Add::add);
+ops!("_Minus", broadcast_sub, try_broadcast_sub, Sub::sub);
+ops!("_Mul", broadcast_mul, try_broadcast_mul, Mul::mul);
+ops!("_Div", broadcast_div, try_broadcast_div, Div::div);
+ops!("_Mod", broadcast_mod, try_broadcast_mod, Rem::rem);
+
+rscalar_ops!("_PlusScalar", Add::add);
+rscalar_ops!("_RMinusScalar", Sub::sub);
+rscalar_ops!("_MulScalar", Mul::mul);
+rscalar_ops!("_RDivScalar", Div::div);
+rscalar_ops!("_RModScalar", Rem::rem);
 
 impl Symbol {
     pub fn new(name: &str) -> Symbol {
+        Symbol::try_new(name).unwrap()
+    }
+
+    pub fn try_new(name: &str) -> Result<Symbol> {
+        let name = CString::new(name)?;
         let mut handle = ptr::null_mut();
-        check_call!(MXSymbolCreateVariable(
-            CString::new(name).unwrap().as_ptr(),
-            &mut handle
-        ));
-        Symbol {
+        try_call!(MXSymbolCreateVariable(name.as_ptr(), &mut handle))?;
+        Ok(Symbol {
             blob: Rc::new(SymBlob::new(handle)),
+        })
+    }
+}
+
+/// Serialization
+impl Symbol {
+    pub fn to_json(&self) -> String {
+        self.try_to_json().unwrap()
+    }
+
+    pub fn try_to_json(&self) -> Result<String> {
+        let mut out_json = ptr::null();
+        try_call!(MXSymbolSaveToJSON(self.handle(), &mut out_json))?;
+        Ok(unsafe { CStr::from_ptr(out_json) }
+            .to_string_lossy()
+            .into_owned())
+    }
+
+    pub fn from_json(json: &str) -> Symbol {
+        Symbol::try_from_json(json).unwrap()
+    }
+
+    pub fn try_from_json(json: &str) -> Result<Symbol> {
+        let json = CString::new(json)?;
+        let mut handle = ptr::null_mut();
+        try_call!(MXSymbolCreateFromJSON(json.as_ptr(), &mut handle))?;
+        Ok(Symbol::from(handle))
+    }
+
+    /// Save to a json file, like the `-symbol.json` written by `HybridBlock.export()`.
+    pub fn save(&self, path: impl AsRef<Path>) {
+        self.try_save(path).unwrap()
+    }
+
+    pub fn try_save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let fname = path_to_cstring(path.as_ref())?;
+        try_call!(MXSymbolSaveToFile(self.handle(), fname.as_ptr()))
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Symbol {
+        Symbol::try_load(path).unwrap()
+    }
+
+    pub fn try_load(path: impl AsRef<Path>) -> Result<Symbol> {
+        let fname = path_to_cstring(path.as_ref())?;
+        let mut handle = ptr::null_mut();
+        try_call!(MXSymbolCreateFromFile(fname.as_ptr(), &mut handle))?;
+        Ok(Symbol::from(handle))
+    }
+}
+
+/// Inference
+impl Symbol {
+    /// Infer the shapes of all arguments, outputs and auxiliary states
+    /// from the shapes of some arguments.
+    pub fn infer_shape(&self, known: &HashMap<&str, &[u32]>) -> Shapes {
+        self.try_infer_shape(known).unwrap()
+    }
+
+    pub fn try_infer_shape(&self, known: &HashMap<&str, &[u32]>) -> Result<Shapes> {
+        let (shapes, complete) = self.infer_shape_impl(known, false)?;
+        if !complete {
+            let unknown = self
+                .try_list_arguments()?
+                .into_iter()
+                .zip(&shapes.0)
+                .filter(|(_, shape)| shape.is_empty() || shape.contains(&0))
+                .map(|(name, _)| name)
+                .collect::<Vec<_>>();
+            return Err(Error::new(
+                ErrorKind::InvalidParameter,
+                format!(
+                    "cannot infer shapes of {:?}, please provide more input shapes",
+                    unknown
+                ),
+            ));
+        }
+        Ok(shapes)
+    }
+
+    /// Like `infer_shape`, but unknown shapes are left empty and unknown
+    /// dimensions are 0 instead of failing.
+    pub fn infer_shape_partial(&self, known: &HashMap<&str, &[u32]>) -> Shapes {
+        self.try_infer_shape_partial(known).unwrap()
+    }
+
+    pub fn try_infer_shape_partial(&self, known: &HashMap<&str, &[u32]>) -> Result<Shapes> {
+        Ok(self.infer_shape_impl(known, true)?.0)
+    }
+
+    /// Infer the types of all arguments, outputs and auxiliary states
+    /// from the types of some arguments.
+    pub fn infer_type(&self, known: &HashMap<&str, DType>) -> Types {
+        self.try_infer_type(known).unwrap()
+    }
+
+    pub fn try_infer_type(&self, known: &HashMap<&str, DType>) -> Result<Types> {
+        let (types, complete) = self.infer_type_impl(known)?;
+        if !complete {
+            return Err(Error::new(
+                ErrorKind::TypeMismatch,
+                format!(
+                    "cannot infer all types from {:?}, please provide more input types",
+                    known
+                ),
+            ));
+        }
+        Ok(types)
+    }
+
+    /// Types that can't be inferred are `DType::None`.
+    pub(crate) fn infer_type_impl(&self, known: &HashMap<&str, DType>) -> Result<(Types, bool)> {
+        let keys = known
+            .keys()
+            .map(|key| CString::new(*key))
+            .collect::<result::Result<Vec<_>, _>>()?;
+        let mut key_ptrs = keys.iter().map(|key| key.as_ptr()).collect::<Vec<_>>();
+        let type_data = known
+            .values()
+            .map(|dtype| *dtype as i32)
+            .collect::<Vec<_>>();
+
+        let mut in_type_size = 0;
+        let mut in_type_data = ptr::null();
+        let mut out_type_size = 0;
+        let mut out_type_data = ptr::null();
+        let mut aux_type_size = 0;
+        let mut aux_type_data = ptr::null();
+        let mut complete = 0;
+
+        try_call!(MXSymbolInferType(
+            self.handle(),
+            keys.len() as u32,
+            key_ptrs.as_mut_ptr(),
+            type_data.as_ptr(),
+            &mut in_type_size,
+            &mut in_type_data,
+            &mut out_type_size,
+            &mut out_type_data,
+            &mut aux_type_size,
+            &mut aux_type_data,
+            &mut complete
+        ))
+        .map_err(|e| e.context(format!("infer_type error, known types: {:?}", known)))?;
+
+        let to_dtypes = |size: u32, data: *const i32| -> Result<Vec<DType>> {
+            if size == 0 {
+                return Ok(Vec::new());
+            }
+            unsafe { slice::from_raw_parts(data, size as usize) }
+                .iter()
+                .map(|dtype| DType::try_from(*dtype))
+                .collect()
+        };
+
+        let types = (
+            to_dtypes(in_type_size, in_type_data)?,
+            to_dtypes(out_type_size, out_type_data)?,
+            to_dtypes(aux_type_size, aux_type_data)?,
+        );
+        Ok((types, complete != 0))
+    }
+
+    fn infer_shape_impl(
+        &self,
+        known: &HashMap<&str, &[u32]>,
+        partial: bool,
+    ) -> Result<(Shapes, bool)> {
+        let mut keys = Vec::with_capacity(known.len());
+        // Shapes are passed in CSR format.
+        let mut arg_ind_ptr = vec![0];
+        let mut arg_shape_data = Vec::new();
+        for (key, shape) in known {
+            keys.push(CString::new(*key)?);
+            arg_shape_data.extend_from_slice(shape);
+            arg_ind_ptr.push(arg_shape_data.len() as u32);
+        }
+        let mut key_ptrs = keys.iter().map(|key| key.as_ptr()).collect::<Vec<_>>();
+
+        let mut in_shape_size = 0;
+        let mut in_shape_ndim = ptr::null();
+        let mut in_shape_data = ptr::null_mut();
+        let mut out_shape_size = 0;
+        let mut out_shape_ndim = ptr::null();
+        let mut out_shape_data = ptr::null_mut();
+        let mut aux_shape_size = 0;
+        let mut aux_shape_ndim = ptr::null();
+        let mut aux_shape_data = ptr::null_mut();
+        let mut complete = 0;
+
+        let ret = if partial {
+            try_call!(MXSymbolInferShapePartial(
+                self.handle(),
+                keys.len() as u32,
+                key_ptrs.as_mut_ptr(),
+                arg_ind_ptr.as_ptr(),
+                arg_shape_data.as_ptr(),
+                &mut in_shape_size,
+                &mut in_shape_ndim,
+                &mut in_shape_data,
+                &mut out_shape_size,
+                &mut out_shape_ndim,
+                &mut out_shape_data,
+                &mut aux_shape_size,
+                &mut aux_shape_ndim,
+                &mut aux_shape_data,
+                &mut complete
+            ))
+        } else {
+            try_call!(MXSymbolInferShape(
+                self.handle(),
+                keys.len() as u32,
+                key_ptrs.as_mut_ptr(),
+                arg_ind_ptr.as_ptr(),
+                arg_shape_data.as_ptr(),
+                &mut in_shape_size,
+                &mut in_shape_ndim,
+                &mut in_shape_data,
+                &mut out_shape_size,
+                &mut out_shape_ndim,
+                &mut out_shape_data,
+                &mut aux_shape_size,
+                &mut aux_shape_ndim,
+                &mut aux_shape_data,
+                &mut complete
+            ))
+        };
+        // MXNet reports the inconsistent operator, add the known shapes.
+        ret.map_err(|e| e.context(format!("infer_shape error, known shapes: {:?}", known)))?;
+
+        let to_shapes = |size: u32, ndim: *const u32, data: *mut *const u32| -> Vec<Vec<u32>> {
+            if size == 0 {
+                return Vec::new();
+            }
+            let ndim = unsafe { slice::from_raw_parts(ndim, size as usize) };
+            let data = unsafe { slice::from_raw_parts(data, size as usize) };
+            ndim.iter()
+                .zip(data)
+                .map(|(ndim, shape)| {
+                    if *ndim == 0 {
+                        Vec::new()
+                    } else {
+                        unsafe { slice::from_raw_parts(*shape, *ndim as usize) }.to_vec()
+                    }
+                })
+                .collect()
+        };
+
+        let shapes = (
+            to_shapes(in_shape_size, in_shape_ndim, in_shape_data),
+            to_shapes(out_shape_size, out_shape_ndim, out_shape_data),
+            to_shapes(aux_shape_size, aux_shape_ndim, aux_shape_data),
+        );
+        Ok((shapes, complete != 0))
+    }
+}
+
+/// Introspection
+impl Symbol {
+    pub fn list_arguments(&self) -> Vec<String> {
+        self.try_list_arguments().unwrap()
+    }
+
+    pub fn try_list_arguments(&self) -> Result<Vec<String>> {
+        let mut size = 0;
+        let mut array = ptr::null_mut();
+        try_call!(MXSymbolListArguments(self.handle(), &mut size, &mut array))?;
+        Ok(to_string_vec(size, array))
+    }
+
+    pub fn list_outputs(&self) -> Vec<String> {
+        self.try_list_outputs().unwrap()
+    }
+
+    pub fn try_list_outputs(&self) -> Result<Vec<String>> {
+        let mut size = 0;
+        let mut array = ptr::null_mut();
+        try_call!(MXSymbolListOutputs(self.handle(), &mut size, &mut array))?;
+        Ok(to_string_vec(size, array))
+    }
+
+    pub fn list_auxiliary_states(&self) -> Vec<String> {
+        self.try_list_auxiliary_states().unwrap()
+    }
+
+    pub fn try_list_auxiliary_states(&self) -> Result<Vec<String>> {
+        let mut size = 0;
+        let mut array = ptr::null_mut();
+        try_call!(MXSymbolListAuxiliaryStates(
+            self.handle(),
+            &mut size,
+            &mut array
+        ))?;
+        Ok(to_string_vec(size, array))
+    }
+
+    /// Arguments and auxiliary states, in the order they appear in the graph.
+    pub fn list_inputs(&self) -> Vec<String> {
+        self.try_list_inputs().unwrap()
+    }
+
+    pub fn try_list_inputs(&self) -> Result<Vec<String>> {
+        let mut size = 0;
+        let mut array = ptr::null_mut();
+        // Option 0 lists all inputs.
+        try_call!(NNSymbolListInputNames(
+            self.handle(),
+            0,
+            &mut size,
+            &mut array
+        ))?;
+        Ok(to_string_vec(size, array))
+    }
+
+    /// Returns None for grouped symbols, which have no name.
+    pub fn name(&self) -> Option<String> {
+        self.try_name().unwrap()
+    }
+
+    pub fn try_name(&self) -> Result<Option<String>> {
+        let mut out = ptr::null();
+        let mut success = 0;
+        try_call!(MXSymbolGetName(self.handle(), &mut out, &mut success))?;
+        Ok(Self::optional_string(out, success))
+    }
+
+    pub fn attr(&self, key: &str) -> Option<String> {
+        self.try_attr(key).unwrap()
+    }
+
+    pub fn try_attr(&self, key: &str) -> Result<Option<String>> {
+        let key = CString::new(key)?;
+        let mut out = ptr::null();
+        let mut success = 0;
+        try_call!(MXSymbolGetAttr(
+            self.handle(),
+            key.as_ptr(),
+            &mut out,
+            &mut success
+        ))?;
+        Ok(Self::optional_string(out, success))
+    }
+
+    pub fn set_attr(&mut self, key: &str, value: &str) -> &mut Self {
+        self.try_set_attr(key, value).unwrap()
+    }
+
+    pub fn try_set_attr(&mut self, key: &str, value: &str) -> Result<&mut Self> {
+        let key = CString::new(key)?;
+        let value = CString::new(value)?;
+        try_call!(MXSymbolSetAttr(self.handle(), key.as_ptr(), value.as_ptr()))?;
+        Ok(self)
+    }
+
+    /// Attributes of this symbol. With `recursive`, attributes of all
+    /// nodes in the graph are listed with keys like `node_name$key`.
+    pub fn list_attr(&self, recursive: bool) -> HashMap<String, String> {
+        self.try_list_attr(recursive).unwrap()
+    }
+
+    pub fn try_list_attr(&self, recursive: bool) -> Result<HashMap<String, String>> {
+        let mut size = 0;
+        let mut pairs = ptr::null_mut();
+        if recursive {
+            try_call!(MXSymbolListAttr(self.handle(), &mut size, &mut pairs))?;
+        } else {
+            try_call!(MXSymbolListAttrShallow(
+                self.handle(),
+                &mut size,
+                &mut pairs
+            ))?;
+        }
+        // Keys and values are interleaved.
+        let pairs = to_string_vec(size * 2, pairs);
+        Ok(pairs
+            .chunks(2)
+            .map(|pair| (pair[0].clone(), pair[1].clone()))
+            .collect())
+    }
+
+    pub fn num_outputs(&self) -> usize {
+        self.try_num_outputs().unwrap()
+    }
+
+    pub fn try_num_outputs(&self) -> Result<usize> {
+        let mut count = 0;
+        try_call!(MXSymbolGetNumOutputs(self.handle(), &mut count))?;
+        Ok(count as usize)
+    }
+
+    /// The `index`-th output, like `sym[index]` in Python.
+    pub fn get_output(&self, index: usize) -> Symbol {
+        self.try_get_output(index).unwrap()
+    }
+
+    pub fn try_get_output(&self, index: usize) -> Result<Symbol> {
+        let mut handle = ptr::null_mut();
+        try_call!(MXSymbolGetOutput(self.handle(), index as u32, &mut handle))?;
+        Ok(Symbol::from(handle))
+    }
+
+    /// A grouped symbol of all the internal nodes, whose outputs are
+    /// named like `fc1_output`.
+    pub fn get_internals(&self) -> Symbol {
+        self.try_get_internals().unwrap()
+    }
+
+    pub fn try_get_internals(&self) -> Result<Symbol> {
+        let mut handle = ptr::null_mut();
+        try_call!(MXSymbolGetInternals(self.handle(), &mut handle))?;
+        Ok(Symbol::from(handle))
+    }
+
+    /// The inputs of the head node as a grouped symbol, None for variables.
+    pub fn get_children(&self) -> Option<Symbol> {
+        self.try_get_children().unwrap()
+    }
+
+    pub fn try_get_children(&self) -> Result<Option<Symbol>> {
+        let mut handle = ptr::null_mut();
+        try_call!(MXSymbolGetChildren(self.handle(), &mut handle))?;
+        if handle.is_null() {
+            return Ok(None);
+        }
+        let children = Symbol::from(handle);
+        if children.try_list_outputs()?.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(children))
+        }
+    }
+
+    fn optional_string(out: *const c_char, success: i32) -> Option<String> {
+        if success != 0 && !out.is_null() {
+            Some(
+                unsafe { CStr::from_ptr(out) }
+                    .to_string_lossy()
+                    .into_owned(),
+            )
+        } else {
+            None
         }
     }
 }
@@ -89,3 +641,164 @@ impl GetHandle for Symbol {
         self.blob.handle()
     }
 }
+
+// Typed wrappers for every registered operator, see `build/op_gen.rs`.
+include!(concat!(env!("OUT_DIR"), "/symbol_op.rs"));
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn introspection() {
+        let data = Symbol::new("data");
+        let fc = Operator::new("FullyConnected")
+            .set_input("data", &data)
+            .set_param("num_hidden", &10)
+            .create_symbol(Some("fc1"));
+        let mut bn = Operator::new("BatchNorm")
+            .push_input(&fc)
+            .create_symbol(Some("bn"));
+
+        assert_eq!(bn.name(), Some("bn".to_owned()));
+        assert_eq!(
+            bn.list_arguments(),
+            vec!["data", "fc1_weight", "fc1_bias", "bn_gamma", "bn_beta"]
+        );
+        assert_eq!(bn.list_outputs(), vec!["bn_output"]);
+        assert_eq!(
+            bn.list_auxiliary_states(),
+            vec!["bn_moving_mean", "bn_moving_var"]
+        );
+        assert_eq!(bn.list_inputs().len(), 7);
+
+        bn.set_attr("lr_mult", "0.1");
+        assert_eq!(bn.attr("lr_mult"), Some("0.1".to_owned()));
+        assert_eq!(bn.list_attr(false)["lr_mult"], "0.1");
+        assert_eq!(fc.list_attr(true)["fc1$num_hidden"], "10");
+
+        assert!(bn
+            .get_internals()
+            .list_outputs()
+            .contains(&"fc1_output".to_owned()));
+        assert_eq!(
+            fc.get_children().unwrap().list_outputs(),
+            vec!["data", "fc1_weight", "fc1_bias"]
+        );
+        assert!(data.get_children().is_none());
+
+        let internals = bn.get_internals();
+        assert_eq!(internals.num_outputs(), internals.list_outputs().len());
+        assert_eq!(bn.num_outputs(), 1);
+        assert_eq!(bn.get_output(0).list_outputs(), vec!["bn_output"]);
+        assert!(bn.try_get_output(1).is_err());
+    }
+
+    #[test]
+    fn nul_in_strings() {
+        let err = Symbol::try_new("a\0b").err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::InvalidParameter);
+        let mut data = Symbol::new("data");
+        assert!(data.try_set_attr("lr_mult", "0\0").is_err());
+        assert!(data.try_save("data\0.json").is_err());
+    }
+
+    #[test]
+    fn generated_op_creates_missing_inputs() {
+        let data = Symbol::new("data");
+        let fc = op::FullyConnected::new(&data)
+            .num_hidden(10)
+            .name("fc1")
+            .create();
+        assert_eq!(fc.list_arguments(), vec!["data", "fc1_weight", "fc1_bias"]);
+    }
+
+    #[test]
+    fn generated_op_requires_operands() {
+        let a = Symbol::new("a");
+        let b = Symbol::new("b");
+        // Operands are constructor arguments, only learnable inputs are
+        // created when left out.
+        let dot = op::Dot::new(&a, &b).create();
+        assert_eq!(dot.list_arguments(), vec!["a", "b"]);
+        let sum = op::ElemwiseAdd::new(&a, &b).create();
+        assert_eq!(sum.list_arguments(), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn borrowed_ops() {
+        let a = Symbol::new("a");
+        let b = Symbol::new("b");
+        let c = Symbol::new("c");
+
+        let out = &a * &b + &c;
+        assert_eq!(out.list_arguments(), vec!["a", "b", "c"]);
+        let out = 1.0 - &a / 2.0;
+        assert_eq!(out.list_arguments(), vec!["a"]);
+
+        let bias = Symbol::new("bias");
+        let out = a.broadcast_add(&bias);
+        let mut known = HashMap::new();
+        known.insert("a", &[2u32, 3][..]);
+        known.insert("bias", &[1u32, 3][..]);
+        let (_, out_shapes, _) = out.infer_shape(&known);
+        assert_eq!(out_shapes, vec![vec![2, 3]]);
+    }
+
+    #[test]
+    fn json() {
+        let data = Symbol::new("data");
+        let fc = Operator::new("FullyConnected")
+            .set_input("data", &data)
+            .set_param("num_hidden", &10)
+            .create_symbol(Some("fc1"));
+
+        let json = fc.to_json();
+        let loaded = Symbol::from_json(&json);
+        assert_eq!(loaded.list_arguments(), fc.list_arguments());
+        assert_eq!(loaded.to_json(), json);
+
+        let path =
+            std::env::temp_dir().join(format!("mxnet_rs_json_{}-symbol.json", std::process::id()));
+        fc.save(&path);
+        let loaded = Symbol::load(&path);
+        assert_eq!(loaded.list_outputs(), vec!["fc1_output"]);
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(Symbol::try_from_json("not json").is_err());
+    }
+
+    #[test]
+    fn infer() {
+        let data = Symbol::new("data");
+        let fc = Operator::new("FullyConnected")
+            .set_input("data", &data)
+            .set_param("num_hidden", &10)
+            .create_symbol(Some("fc1"));
+
+        let mut known = HashMap::new();
+        known.insert("data", &[2u32, 5][..]);
+        let (arg_shapes, out_shapes, aux_shapes) = fc.infer_shape(&known);
+        assert_eq!(arg_shapes, vec![vec![2, 5], vec![10, 5], vec![10]]);
+        assert_eq!(out_shapes, vec![vec![2, 10]]);
+        assert!(aux_shapes.is_empty());
+
+        known.insert("fc1_weight", &[3u32, 3][..]);
+        let err = fc.try_infer_shape(&known).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::ShapeMismatch);
+        // The known shapes come first, then what MXNet reported.
+        let (context, reported) = err.message().split_once('\n').unwrap();
+        assert!(context.starts_with("infer_shape error"));
+        assert!(reported.contains("Error in operator fc1"));
+
+        assert!(fc.try_infer_shape(&HashMap::new()).is_err());
+        let (arg_shapes, _, _) = fc.infer_shape_partial(&HashMap::new());
+        assert_eq!(arg_shapes.len(), 3);
+
+        let mut known = HashMap::new();
+        known.insert("data", DType::F64);
+        let (arg_types, out_types, _) = fc.infer_type(&known);
+        assert_eq!(arg_types, vec![DType::F64; 3]);
+        assert_eq!(out_types, vec![DType::F64]);
+    }
+}